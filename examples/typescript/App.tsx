@@ -0,0 +1,78 @@
+// Checked with `npm run typecheck` against the declarations in lib/, without being bundled
+import Reactron, { Component, FunctionComponent } from "reactron";
+
+interface Todo {
+  id: number;
+  text: string;
+  completed: boolean;
+}
+
+function TodoItem({ todo, onToggle }: { todo: Todo; onToggle: (id: number) => void }) {
+  return (
+    <li className={todo.completed ? "completed" : ""}>
+      <input type="checkbox" checked={todo.completed} onChange={() => onToggle(todo.id)} />
+      <label>{todo.text}</label>
+    </li>
+  );
+}
+
+const MemoTodoItem = Reactron.memo(TodoItem, (prev, next) => prev.todo === next.todo);
+
+const Counter: FunctionComponent<{ label: string }> = ({ label }) => {
+  let [count, setCount] = Reactron.useState(0);
+
+  return <button onClick={() => setCount(count + 1)}>{`${label}: ${count}`}</button>;
+};
+
+interface TimerState {
+  seconds: number;
+}
+
+class Timer extends Component<{ step: number }, TimerState> {
+  state = { seconds: 0 };
+
+  componentDidUpdate(prevProps: { step: number }, prevState: TimerState) {
+    if (prevState.seconds > 60) {
+      this.setState({ seconds: 0 });
+    }
+  }
+
+  render() {
+    return <p onClick={() => this.setState((state, props) => ({ seconds: state.seconds + props.step }))}>{`${this.state.seconds}s`}</p>;
+  }
+}
+
+const LazyCounter = Reactron.lazy(() => Promise.resolve({ default: Counter }));
+
+function App({ todos }: { todos: Todo[] }) {
+  let toggle = (id: number) => console.log(id);
+
+  return (
+    <>
+      <ul>
+        {todos.map((todo) => <MemoTodoItem key={todo.id} todo={todo} onToggle={toggle} />)}
+      </ul>
+      <Counter label="Clicks" />
+      <Timer step={1} />
+      {Reactron.createElement(Reactron.Suspense, { fallback: <p>Loading</p> }, <LazyCounter label="Later" />)}
+    </>
+  );
+}
+
+// Misuses the declarations have to reject
+// @ts-expect-error `checked` is a boolean
+let badInput = <input checked="yes" />;
+// @ts-expect-error `label` is required
+let badCounter = <Counter />;
+// @ts-expect-error the state has no `minutes`
+let badState = (timer: Timer) => timer.setState({ minutes: 1 });
+
+Reactron.load({ scheduler: "MessageChannel" }).then(() => {
+  let root = Reactron.createRoot(document.getElementById("app")!);
+  root.render(<App todos={[{ id: 1, text: "Type the API", completed: true }]} />);
+
+  let html: string = Reactron.renderToString(<App todos={[]} />);
+  let stream = new ReadableStream(Reactron.renderToStream(<App todos={[]} />));
+
+  console.log(html, stream, badInput, badCounter, badState);
+});
@@ -0,0 +1,184 @@
+// Element built by the wasm module. It's consumed once rendered or passed as a child,
+// so it can't be used twice.
+export type ReactronElement = number & { readonly __reactron: "Element" };
+
+export type Key = string | number;
+
+export type Child = ReactronElement | string | null | undefined | false | Child[];
+
+export type SchedulerName = "IdleCallback" | "MessageChannel" | "AnimationFrame" | "Manual";
+
+declare const FragmentType: unique symbol;
+declare const StrictModeType: unique symbol;
+declare const SuspenseType: unique symbol;
+declare const MemoType: unique symbol;
+
+export interface Attributes {
+  key?: Key;
+}
+
+export interface HTMLAttributes extends Attributes {
+  className?: string;
+  children?: Child;
+  onClick?: (event: MouseEvent) => void;
+  onChange?: (event: Event) => void;
+  onBlur?: (event: FocusEvent) => void;
+  onKeyDown?: (event: KeyboardEvent) => void;
+}
+
+export interface ButtonHTMLAttributes extends HTMLAttributes {
+  type?: "button" | "submit" | "reset";
+}
+
+export interface InputHTMLAttributes extends HTMLAttributes {
+  type?: string;
+  value?: string;
+  checked?: boolean;
+  placeholder?: string;
+}
+
+export interface TextareaHTMLAttributes extends HTMLAttributes {
+  value?: string;
+  placeholder?: string;
+}
+
+export interface OptionHTMLAttributes extends HTMLAttributes {
+  value?: string;
+}
+
+// Props each host element supports, with the common ones for the tags not listed
+export interface IntrinsicElements {
+  a: HTMLAttributes;
+  button: ButtonHTMLAttributes;
+  div: HTMLAttributes;
+  footer: HTMLAttributes;
+  form: HTMLAttributes;
+  h1: HTMLAttributes;
+  h2: HTMLAttributes;
+  h3: HTMLAttributes;
+  header: HTMLAttributes;
+  input: InputHTMLAttributes;
+  label: HTMLAttributes;
+  li: HTMLAttributes;
+  option: OptionHTMLAttributes;
+  p: HTMLAttributes;
+  section: HTMLAttributes;
+  select: OptionHTMLAttributes;
+  span: HTMLAttributes;
+  strong: HTMLAttributes;
+  textarea: TextareaHTMLAttributes;
+  ul: HTMLAttributes;
+  [tagName: string]: HTMLAttributes;
+}
+
+export type FunctionComponent<P = {}> = (props: P & { children?: ReactronElement[] }) => ReactronElement | null;
+
+// Not a function, the call signature only lets JSX take it as a tag
+export interface MemoComponent<P = {}> {
+  (props: P & { children?: ReactronElement[] }): ReactronElement | null;
+  readonly $$typeof: typeof MemoType;
+  component: FunctionComponent<P>;
+  compare?: (prevProps: P, nextProps: P) => boolean;
+}
+
+export declare class Component<P = {}, S = {}> {
+  constructor(props: P);
+
+  readonly props: P & { children?: ReactronElement[] };
+  state: S;
+
+  setState(partialState: Partial<S> | ((prevState: S, props: P) => Partial<S>)): void;
+  render(): ReactronElement | null;
+
+  componentDidMount?(): void;
+  componentDidUpdate?(prevProps: P, prevState: S): void;
+  componentWillUnmount?(): void;
+}
+
+export type ComponentClass<P = {}> = new (props: P) => Component<P, any>;
+
+export type ComponentType<P = {}> = FunctionComponent<P> | MemoComponent<P> | ComponentClass<P>;
+
+export interface SuspenseProps extends Attributes {
+  fallback?: Child;
+  children?: Child;
+}
+
+export type SetState<T> = (value: T) => void;
+
+export interface Root {
+  render(element: ReactronElement): void;
+  unmount(): void;
+}
+
+// Named apart so the JSX namespace can refer to it
+type HostElements = IntrinsicElements;
+
+export namespace JSX {
+  type Element = ReactronElement;
+
+  interface IntrinsicElements extends HostElements {}
+
+  interface IntrinsicAttributes extends Attributes {}
+
+  interface ElementChildrenAttribute {
+    children: {};
+  }
+}
+
+export interface JsxFunction {
+  <K extends keyof IntrinsicElements>(type: K, props: IntrinsicElements[K], key?: Key): ReactronElement;
+  <P>(type: ComponentType<P>, props: P & { children?: Child }, key?: Key): ReactronElement;
+  (type: typeof FragmentType | typeof StrictModeType, props: { children?: Child }, key?: Key): ReactronElement;
+  (type: typeof SuspenseType, props: SuspenseProps, key?: Key): ReactronElement;
+}
+
+export interface JsxDevFunction {
+  <K extends keyof IntrinsicElements>(type: K, props: IntrinsicElements[K], key?: Key, isStaticChildren?: boolean): ReactronElement;
+  <P>(type: ComponentType<P>, props: P & { children?: Child }, key?: Key, isStaticChildren?: boolean): ReactronElement;
+  (type: typeof FragmentType | typeof StrictModeType, props: { children?: Child }, key?: Key, isStaticChildren?: boolean): ReactronElement;
+  (type: typeof SuspenseType, props: SuspenseProps, key?: Key, isStaticChildren?: boolean): ReactronElement;
+}
+
+export interface Reactron {
+  load(options?: { scheduler?: SchedulerName }): Promise<void>;
+
+  render(element: ReactronElement, container: Element): void;
+  hydrate(element: ReactronElement, container: Element): void;
+  createRoot(container: Element): Root;
+
+  useState<T>(initialValue: T): [T, SetState<T>];
+  useRef<T>(initialValue: T): { current: T };
+
+  createElement<K extends keyof IntrinsicElements>(
+    type: K,
+    props?: IntrinsicElements[K] | null,
+    ...children: Child[]
+  ): ReactronElement;
+  createElement<P>(type: ComponentType<P>, props?: (P & Attributes) | null, ...children: Child[]): ReactronElement;
+  createElement(type: typeof FragmentType | typeof StrictModeType, props?: Attributes | null, ...children: Child[]): ReactronElement;
+  createElement(type: typeof SuspenseType, props?: Omit<SuspenseProps, "children"> | null, ...children: Child[]): ReactronElement;
+
+  jsx: JsxFunction;
+  jsxs: JsxFunction;
+  jsxDEV: JsxDevFunction;
+  readonly Fragment: typeof FragmentType;
+  readonly StrictMode: typeof StrictModeType;
+  readonly Suspense: typeof SuspenseType;
+  readonly Component: typeof Component;
+
+  memo<P>(component: FunctionComponent<P>, compare?: (prevProps: P, nextProps: P) => boolean): MemoComponent<P>;
+  lazy<P>(loader: () => Promise<{ default: ComponentType<P> }>): FunctionComponent<P>;
+
+  flushWork(): void;
+  flushSync<T>(callback: () => T): T;
+  startTransition(callback: () => void): void;
+
+  renderToString(element: ReactronElement): string;
+  // Underlying source for a `ReadableStream` of HTML chunks
+  renderToStream(element: ReactronElement): UnderlyingDefaultSource<string>;
+}
+
+declare const Reactron: Reactron;
+
+export default Reactron;
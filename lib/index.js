@@ -1,70 +1,320 @@
 let context;
-let workLoop;
+let discreteListeners = new WeakMap();
+const MEMO_TYPE = Symbol("reactron.memo");
+const FRAGMENT_TYPE = Symbol("reactron.fragment");
+const STRICT_MODE_TYPE = Symbol("reactron.strict_mode");
+const SUSPENSE_TYPE = Symbol("reactron.suspense");
 
-export default {
+// Base class of class components. The instance is created by the reconciler, which keeps its
+// state and hands `setState` partial states over to be merged on the next render.
+class Component {
+  constructor(props) {
+    this.props = props;
+    this.state = {};
+  }
+
+  setState(partialState) {
+    if (!this.__reactronUpdater) {
+      throw new Error("Reactron: 'setState' called on a component that isn't mounted");
+    }
+
+    this.__reactronUpdater(partialState);
+  }
+}
+
+Component.prototype.isReactronComponent = true;
+
+export { Component };
+
+const Reactron = {
   render() {
     throw new Error("Reactron: 'render' used before loading wasm module");
   },
 
+  hydrate() {
+    throw new Error("Reactron: 'hydrate' used before loading wasm module");
+  },
+
+  createRoot() {
+    throw new Error("Reactron: 'createRoot' used before loading wasm module");
+  },
+
   useState() {
     throw new Error("Reactron: 'useState' used before loading wasm module");
   },
 
+  useRef() {
+    throw new Error("Reactron: 'useRef' used before loading wasm module");
+  },
+
   createElement() {
     throw new Error("Reactron: 'createElement' used before loading wasm module");
   },
 
-  load() {
-    return import("../pkg/reactron_bg.js").then((glue) => {
-      context = glue.get_context();
+  jsx() {
+    throw new Error("Reactron: 'jsx' used before loading wasm module");
+  },
 
-      workLoop = (deadline) => {
-        context = glue.work_loop(context, deadline.didTimeout);
-        window.requestIdleCallback(workLoop);
-      };
+  jsxs() {
+    throw new Error("Reactron: 'jsxs' used before loading wasm module");
+  },
+
+  jsxDEV() {
+    throw new Error("Reactron: 'jsxDEV' used before loading wasm module");
+  },
+
+  Fragment: FRAGMENT_TYPE,
+
+  // Renders function components twice and mounts class components twice in debug builds
+  StrictMode: STRICT_MODE_TYPE,
+
+  // Renders its `fallback` while any component below it waits for a promise it threw
+  Suspense: SUSPENSE_TYPE,
+
+  Component,
+
+  memo(component, compare) {
+    return { $$typeof: MEMO_TYPE, component, compare };
+  },
+
+  // Component loading the one it renders the first time it's rendered, suspending until then
+  lazy(loader) {
+    let status = "pending";
+    let result;
+    let promise;
+
+    return function Lazy(props) {
+      if (!promise) {
+        promise = loader().then(
+          (module) => {
+            status = "resolved";
+            result = module.default || module;
+          },
+          (error) => {
+            status = "rejected";
+            result = error;
+          },
+        );
+      }
+
+      if (status === "pending") {
+        throw promise;
+      } else if (status === "rejected") {
+        throw result;
+      }
+
+      // Looked up when rendering, as `createElement` is only defined once the module loaded
+      return Reactron.createElement(result, props);
+    };
+  },
+
+  flushWork() {
+    throw new Error("Reactron: 'flushWork' used before loading wasm module");
+  },
+
+  flushSync() {
+    throw new Error("Reactron: 'flushSync' used before loading wasm module");
+  },
+
+  startTransition() {
+    throw new Error("Reactron: 'startTransition' used before loading wasm module");
+  },
+
+  renderToString() {
+    throw new Error("Reactron: 'renderToString' used before loading wasm module");
+  },
+
+  renderToStream() {
+    throw new Error("Reactron: 'renderToStream' used before loading wasm module");
+  },
+
+  load({ scheduler } = {}) {
+    return import("../pkg/reactron_bg.js").then((glue) => {
+      // Pick the scheduler by name ("IdleCallback", "MessageChannel", "AnimationFrame"
+      // or "Manual"), or let the context detect the best one for this browser. Servers
+      // without a window get the manual one.
+      context = glue.get_context(scheduler ? glue.SchedulerKind[scheduler] : undefined);
 
       this.render = (element, parentDom) => {
         context = glue.render(context, element, parentDom);
-        window.requestIdleCallback(workLoop);
+      };
+
+      this.hydrate = (element, parentDom) => {
+        context = glue.hydrate(context, element, parentDom);
+      };
+
+      this.createRoot = (container) => {
+        let rootId = glue.create_root(context, container);
+
+        return {
+          render(element) {
+            glue.render_root(context, rootId, element);
+          },
+
+          unmount() {
+            glue.unmount(context, rootId);
+          },
+        };
       };
 
       this.useState = (initialValue) => {
-        let result = glue.use_state(context, initialValue);
-        return result;
+        return glue.use_state(context, initialValue);
       };
 
-      this.createElement = (type, props, ...rawChildren) => {
-        props = props || {};
-        let children = rawChildren
-          .flat()
-          .filter((x) => x)
-          .map((x) => {
-            return typeof x === "string"
-              ? glue.create_text_element(x)
-              : x;
+      this.useRef = (initialValue) => {
+        return glue.use_ref(context, initialValue);
+      };
+
+      this.flushWork = () => {
+        glue.flush_work(context);
+      };
+
+      this.flushSync = (callback) => {
+        return glue.flush_sync(context, callback);
+      };
+
+      this.startTransition = (callback) => {
+        glue.run_with_lane(context, glue.Lane.Transition, callback);
+      };
+
+      this.renderToString = (element) => {
+        return glue.render_to_string(context, element);
+      };
+
+      // Underlying source for a `ReadableStream`, which renders a chunk every time it is pulled
+      this.renderToStream = (element) => {
+        let stream = glue.render_to_stream(context, element);
+
+        return {
+          pull(controller) {
+            let chunk;
+
+            // A render that fails errors the stream
+            try {
+              chunk = stream.next_chunk();
+            } catch (error) {
+              stream.free();
+              controller.error(error);
+              return;
+            }
+
+            if (chunk === undefined) {
+              stream.free();
+              controller.close();
+            } else {
+              controller.enqueue(chunk);
+            }
+          },
+
+          cancel() {
+            stream.free();
+          },
+        };
+      };
+
+      // Updates made by event listeners are user input, so they get the discrete lane.
+      // Wrappers are cached so the listener identity stays the same between renders.
+      let discrete = (listener) => {
+        if (!listener) {
+          return listener;
+        }
+
+        if (!discreteListeners.has(listener)) {
+          discreteListeners.set(listener, (event) => {
+            return glue.run_with_lane(context, glue.Lane.Discrete, () => listener(event));
           });
+        }
+
+        return discreteListeners.get(listener);
+      };
+
+      // Children written out one by one are given in `rawChildren`, while arrays among them
+      // are lists built at runtime, whose items need keys
+      let toElements = (rawChildren) => {
+        let elements = [];
+
+        let add = (child, isListItem) => {
+          if (Array.isArray(child)) {
+            child.forEach((item) => add(item, true));
+          } else if (typeof child === "string") {
+            elements.push(glue.create_text_element(child));
+          } else if (child) {
+            elements.push(isListItem ? glue.mark_list_item(child) : child);
+          }
+        };
+
+        rawChildren.forEach((child) => add(child, false));
+
+        return elements;
+      };
 
+      let createElement = (type, props, children, key) => {
+        let isMemoComponent = type && type.$$typeof === MEMO_TYPE;
+        let isClassComponent = typeof type === "function" && type.prototype && type.prototype.isReactronComponent;
         let isFunctionalComponent = typeof type === "function";
+        let element;
 
-        if (isFunctionalComponent) {
+        // Children are left out when there are none so memoized props can compare equal
+        if (children.length > 0 && (isFunctionalComponent || isMemoComponent)) {
           props.children = children;
-          return glue.create_functional_component(type, props);
+        }
+
+        if (type === FRAGMENT_TYPE) {
+          element = glue.create_fragment(children);
+        } else if (type === STRICT_MODE_TYPE) {
+          element = glue.create_strict_mode(children);
+        } else if (type === SUSPENSE_TYPE) {
+          element = glue.create_suspense(toElements([props.fallback]), children);
+        } else if (isClassComponent) {
+          element = glue.create_class_component(type, props);
+        } else if (isMemoComponent) {
+          element = glue.memo(glue.create_functional_component(type.component, props), type.compare);
+        } else if (isFunctionalComponent) {
+          element = glue.create_functional_component(type, props);
         } else {
           let elementProps = glue.create_props(
-            props ? props.className : null,
-            props ? props.nodeValue: null,
-            props ? props.onClick : null,
-            props ? props.onChange : null,
-            props ? props.onBlur : null,
-            props ? props.onKeyDown : null,
-            props ? props.type : null,
-            props ? props.value : null,
-            props ? props.checked : null,
-            props ? props.placeholder : null,
+            props.className,
+            props.nodeValue,
+            discrete(props.onClick),
+            discrete(props.onChange),
+            discrete(props.onBlur),
+            discrete(props.onKeyDown),
+            props.type,
+            props.value,
+            props.checked,
+            props.placeholder,
           );
-          return glue.create_element(type, elementProps, children);
+          element = glue.create_element(type, elementProps, children);
         }
+
+        return key == null ? element : glue.set_key(element, String(key));
+      };
+
+      this.createElement = (type, props, ...rawChildren) => {
+        let { key, ...rest } = props || {};
+
+        return createElement(type, rest, toElements(rawChildren), key);
+      };
+
+      // Automatic JSX runtime, which passes the children in the props and the key apart.
+      // `jsx` gets a single child, which is a list if it's an array, and `jsxs` static children.
+      this.jsx = (type, props, key) => {
+        let { children, ...rest } = props || {};
+
+        return createElement(type, rest, toElements([children]), key);
+      };
+
+      this.jsxs = (type, props, key) => {
+        let { children, ...rest } = props || {};
+
+        return createElement(type, rest, toElements(children), key);
+      };
+
+      this.jsxDEV = (type, props, key, isStaticChildren) => {
+        return isStaticChildren ? this.jsxs(type, props, key) : this.jsx(type, props, key);
       };
     });
   }
-}
+};
+
+export default Reactron;
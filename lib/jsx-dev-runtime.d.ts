@@ -0,0 +1,6 @@
+import Reactron, { JsxDevFunction } from "./index";
+
+export { JSX } from "./index";
+
+export const Fragment: typeof Reactron.Fragment;
+export const jsxDEV: JsxDevFunction;
@@ -0,0 +1,7 @@
+import Reactron from "./index.js";
+
+export const Fragment = Reactron.Fragment;
+
+export function jsxDEV(type, props, key, isStaticChildren) {
+  return Reactron.jsxDEV(type, props, key, isStaticChildren);
+}
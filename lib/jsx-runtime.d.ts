@@ -0,0 +1,7 @@
+import Reactron, { JsxFunction } from "./index";
+
+export { JSX } from "./index";
+
+export const Fragment: typeof Reactron.Fragment;
+export const jsx: JsxFunction;
+export const jsxs: JsxFunction;
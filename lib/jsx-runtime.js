@@ -0,0 +1,12 @@
+import Reactron from "./index.js";
+
+// Entry point for `jsxImportSource: "reactron"`, usable once the wasm module is loaded
+export const Fragment = Reactron.Fragment;
+
+export function jsx(type, props, key) {
+  return Reactron.jsx(type, props, key);
+}
+
+export function jsxs(type, props, key) {
+  return Reactron.jsxs(type, props, key);
+}
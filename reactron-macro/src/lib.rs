@@ -0,0 +1,293 @@
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned};
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::spanned::Spanned;
+use syn::{braced, parse_macro_input, Error, Expr, Ident, LitBool, LitStr, Path, Result, Token};
+
+/// Builds a `Box<Element>` tree with a JSX-like syntax:
+///
+/// ```ignore
+/// html! {
+///     <ul class="todo-list">
+///         <li>{ "Static text" }</li>
+///         { for todos.iter().map(|todo| html! { <li>{ &todo.title }</li> }) }
+///         <Counter initial={3} />
+///     </ul>
+/// }
+/// ```
+///
+/// Lowercase tags are host elements, whose attributes are checked against the props elements
+/// support, and `key` tells siblings apart. Capitalized tags and paths are Rust components, built like a struct literal from
+/// their attributes. Children are text literals or `{ expr }` blocks of anything implementing
+/// `IntoChildren`, and `{ for expr }` adds every item of an iterator.
+#[proc_macro]
+pub fn html(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let node = parse_macro_input!(input as HtmlNode);
+
+    node.expand().into()
+}
+
+enum HtmlNode {
+    Element(HtmlElement),
+    Component(HtmlComponent),
+    Text(LitStr),
+    Block(Expr),
+    Iterable(Expr),
+}
+
+struct HtmlElement {
+    tag: Ident,
+    attributes: Vec<HtmlAttribute>,
+    children: Vec<HtmlNode>,
+}
+
+struct HtmlComponent {
+    path: Path,
+    attributes: Vec<HtmlAttribute>,
+}
+
+struct HtmlAttribute {
+    name: Ident,
+    value: AttributeValue,
+}
+
+enum AttributeValue {
+    Text(LitStr),
+    Bool(LitBool),
+    Expr(Expr),
+    // Attribute without a value, like `checked`
+    Present(Span),
+}
+
+impl Parse for HtmlNode {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let node = HtmlNode::parse_child(input)?;
+
+        if !input.is_empty() {
+            return Err(input.error("html! expects a single root node, wrap siblings in an element"));
+        }
+
+        Ok(node)
+    }
+}
+
+impl HtmlNode {
+    fn parse_child(input: ParseStream) -> Result<Self> {
+        if input.peek(LitStr) {
+            return Ok(HtmlNode::Text(input.parse()?));
+        }
+
+        if input.peek(syn::token::Brace) {
+            let content;
+            braced!(content in input);
+
+            if content.peek(Token![for]) {
+                content.parse::<Token![for]>()?;
+                return Ok(HtmlNode::Iterable(content.parse()?));
+            }
+
+            return Ok(HtmlNode::Block(content.parse()?));
+        }
+
+        if !input.peek(Token![<]) {
+            return Err(input.error("expected an element, a quoted text or a `{ ... }` block"));
+        }
+
+        input.parse::<Token![<]>()?;
+        let path = input.call(Path::parse_mod_style)?;
+        let attributes = parse_attributes(input)?;
+
+        let is_component = path.segments.len() > 1 || path.segments[0].ident
+            .to_string()
+            .starts_with(|c: char| c.is_ascii_uppercase());
+
+        if is_component {
+            if !input.peek(Token![/]) {
+                return Err(Error::new(path.span(), "components can't have children, pass them as a prop instead"));
+            }
+
+            input.parse::<Token![/]>()?;
+            input.parse::<Token![>]>()?;
+
+            return Ok(HtmlNode::Component(HtmlComponent { path, attributes }));
+        }
+
+        let tag = path.segments[0].ident.clone();
+        let mut children = Vec::new();
+
+        if input.peek(Token![/]) {
+            input.parse::<Token![/]>()?;
+            input.parse::<Token![>]>()?;
+
+            return Ok(HtmlNode::Element(HtmlElement { tag, attributes, children }));
+        }
+
+        input.parse::<Token![>]>()?;
+
+        while !(input.peek(Token![<]) && input.peek2(Token![/])) {
+            if input.is_empty() {
+                return Err(Error::new(tag.span(), format!("<{}> is never closed", tag)));
+            }
+
+            children.push(HtmlNode::parse_child(input)?);
+        }
+
+        input.parse::<Token![<]>()?;
+        input.parse::<Token![/]>()?;
+        let closing_tag = input.call(Ident::parse_any)?;
+
+        if closing_tag != tag {
+            return Err(Error::new(closing_tag.span(), format!("expected </{}>", tag)));
+        }
+
+        input.parse::<Token![>]>()?;
+
+        Ok(HtmlNode::Element(HtmlElement { tag, attributes, children }))
+    }
+
+    fn expand(&self) -> TokenStream {
+        match self {
+            HtmlNode::Element(element) => element.expand(),
+            HtmlNode::Component(component) => component.expand(),
+            HtmlNode::Text(text) => quote! { ::reactron::Element::text(#text) },
+            HtmlNode::Block(expr) => quote_spanned! {expr.span()=>
+                {
+                    let mut children: ::std::vec::Vec<::std::boxed::Box<::reactron::Element>> = ::std::vec::Vec::new();
+                    ::reactron::IntoChildren::into_children(#expr, &mut children);
+
+                    match children.len() {
+                        1 => children.remove(0),
+                        _ => panic!("Reactron: html! needs a single root node"),
+                    }
+                }
+            },
+            HtmlNode::Iterable(expr) => Error::new(expr.span(), "`{ for ... }` can only be used as a child").to_compile_error(),
+        }
+    }
+
+    /// Builder call adding the node to the children of its element
+    fn expand_as_child(&self) -> TokenStream {
+        match self {
+            HtmlNode::Block(expr) => quote_spanned! {expr.span()=> .child(#expr) },
+            HtmlNode::Iterable(expr) => quote_spanned! {expr.span()=> .children(#expr) },
+            node => {
+                let node = node.expand();
+
+                quote! { .child(#node) }
+            },
+        }
+    }
+}
+
+fn parse_attributes(input: ParseStream) -> Result<Vec<HtmlAttribute>> {
+    let mut attributes = Vec::new();
+
+    while !input.peek(Token![/]) && !input.peek(Token![>]) {
+        let name = input.call(Ident::parse_any)?;
+
+        let value = if input.peek(Token![=]) {
+            input.parse::<Token![=]>()?;
+
+            if input.peek(LitStr) {
+                AttributeValue::Text(input.parse()?)
+            } else if input.peek(LitBool) {
+                AttributeValue::Bool(input.parse()?)
+            } else {
+                let content;
+                braced!(content in input);
+                AttributeValue::Expr(content.parse()?)
+            }
+        } else {
+            AttributeValue::Present(name.span())
+        };
+
+        attributes.push(HtmlAttribute { name, value });
+    }
+
+    Ok(attributes)
+}
+
+impl AttributeValue {
+    fn to_tokens(&self) -> TokenStream {
+        match self {
+            AttributeValue::Text(text) => quote! { #text },
+            AttributeValue::Bool(value) => quote! { #value },
+            AttributeValue::Expr(expr) => quote! { #expr },
+            AttributeValue::Present(span) => quote_spanned! {*span=> true },
+        }
+    }
+}
+
+impl HtmlElement {
+    fn expand(&self) -> TokenStream {
+        let attributes = self.attributes.iter().map(|attribute| {
+            let name = attribute.name.to_string();
+            let value = attribute.value.to_tokens();
+            let span = attribute.name.span();
+
+            match name.as_str() {
+                "class" => quote_spanned! {span=> .class(::std::string::ToString::to_string(&#value)) },
+                "type" => quote_spanned! {span=> .input_type(::std::string::ToString::to_string(&#value)) },
+                "value" => quote_spanned! {span=> .value(::std::string::ToString::to_string(&#value)) },
+                "placeholder" => quote_spanned! {span=> .placeholder(::std::string::ToString::to_string(&#value)) },
+                "key" => quote_spanned! {span=> .key(::std::string::ToString::to_string(&#value)) },
+                "checked" => quote_spanned! {span=> .checked(#value) },
+                "onclick" | "onchange" | "onblur" | "onkeydown" => {
+                    let event_type = &name[2..];
+
+                    quote_spanned! {span=> .on(#event_type, #value) }
+                },
+                _ => {
+                    let message = format!(
+                        "unknown attribute `{}` on <{}>, expected one of: class, type, value, placeholder, checked, key, onclick, onchange, onblur, onkeydown",
+                        name,
+                        self.tag
+                    );
+
+                    Error::new(span, message).to_compile_error()
+                },
+            }
+        });
+
+        let tag = self.tag.to_string();
+        let children = self.children.iter().map(HtmlNode::expand_as_child);
+
+        quote! {
+            ::reactron::Element::tag(#tag)
+                #(#attributes)*
+                #(#children)*
+                .build()
+        }
+    }
+}
+
+impl HtmlComponent {
+    fn expand(&self) -> TokenStream {
+        let path = &self.path;
+        let key = match self.attributes.iter().find(|attribute| attribute.name == "key") {
+            Some(attribute) => {
+                let value = attribute.value.to_tokens();
+
+                quote! { ::std::option::Option::Some(::std::string::ToString::to_string(&#value)) }
+            },
+            None => quote! { ::std::option::Option::None },
+        };
+
+        let fields = self.attributes.iter().filter(|attribute| attribute.name != "key").map(|attribute| {
+            let name = &attribute.name;
+            let value = attribute.value.to_tokens();
+
+            quote_spanned! {name.span()=> #name: ::std::convert::Into::into(#value) }
+        });
+
+        quote! {
+            {
+                let mut element = ::reactron::Element::from_component(#path { #(#fields),* });
+                element.set_key(#key);
+
+                ::std::boxed::Box::new(element)
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use super::Element;
+
+/// Values that can be rendered as children in `html!`: elements, text, and options or vectors of them
+pub trait IntoChildren {
+    fn into_children(self, children: &mut Vec<Box<Element>>);
+}
+
+impl IntoChildren for Box<Element> {
+    fn into_children(self, children: &mut Vec<Box<Element>>) {
+        children.push(self);
+    }
+}
+
+impl IntoChildren for Element {
+    fn into_children(self, children: &mut Vec<Box<Element>>) {
+        children.push(Box::new(self));
+    }
+}
+
+impl IntoChildren for String {
+    fn into_children(self, children: &mut Vec<Box<Element>>) {
+        children.push(Element::text(self));
+    }
+}
+
+impl IntoChildren for &str {
+    fn into_children(self, children: &mut Vec<Box<Element>>) {
+        String::from(self).into_children(children);
+    }
+}
+
+impl IntoChildren for &String {
+    fn into_children(self, children: &mut Vec<Box<Element>>) {
+        self.clone().into_children(children);
+    }
+}
+
+impl<T: IntoChildren> IntoChildren for Option<T> {
+    fn into_children(self, children: &mut Vec<Box<Element>>) {
+        if let Some(child) = self {
+            child.into_children(children);
+        }
+    }
+}
+
+impl<T: IntoChildren> IntoChildren for Vec<T> {
+    fn into_children(self, children: &mut Vec<Box<Element>>) {
+        let start = children.len();
+
+        for child in self {
+            child.into_children(children);
+        }
+
+        for child in &mut children[start..] {
+            child.set_list_item();
+        }
+    }
+}
+
+macro_rules! impl_into_children_for_display {
+    ($($t:ty),*) => {
+        $(
+            impl IntoChildren for $t {
+                fn into_children(self, children: &mut Vec<Box<Element>>) {
+                    self.to_string().into_children(children);
+                }
+            }
+        )*
+    };
+}
+
+impl_into_children_for_display!(char, i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64);
@@ -0,0 +1,116 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use js_sys::{Array, Function, Object, Reflect};
+use std::cell::RefCell;
+use super::Element;
+
+// Property the `Component` base class of the JS side calls `setState` through
+static UPDATER_KEY: &str = "__reactronUpdater";
+
+type Updater = Closure<dyn FnMut(JsValue) -> Result<(), JsValue>>;
+
+/// Instance of a JS class component, created on mount and shared by the fiber and all of its alternates
+pub struct ClassInstance {
+    instance: Object,
+    // Props and state of the last committed render, handed to `componentDidUpdate`. Set
+    // once the instance is mounted.
+    committed: RefCell<Option<(JsValue, JsValue)>>,
+    is_strict: bool,
+    updater: RefCell<Option<Updater>>,
+}
+
+impl ClassInstance {
+    pub fn new(class: &Function, props: &JsValue, is_strict: bool) -> Result<Self, JsValue> {
+        let instance = Reflect::construct(class, &Array::of1(props))?.unchecked_into::<Object>();
+
+        Ok(ClassInstance {
+            instance,
+            committed: RefCell::new(None),
+            is_strict,
+            updater: RefCell::new(None),
+        })
+    }
+
+    /// State set by the constructor, which the state hook starts from
+    pub fn initial_state(&self) -> JsValue {
+        Reflect::get(&self.instance, &JsValue::from_str("state")).unwrap_or(JsValue::undefined())
+    }
+
+    pub fn props(&self) -> JsValue {
+        Reflect::get(&self.instance, &JsValue::from_str("props")).unwrap_or(JsValue::undefined())
+    }
+
+    pub fn has_updater(&self) -> bool {
+        self.updater.borrow().is_some()
+    }
+
+    /// The closure is kept for as long as the instance, which may call it after being unmounted
+    pub fn set_updater(&self, updater: Updater) {
+        let _ = Reflect::set(&self.instance, &JsValue::from_str(UPDATER_KEY), updater.as_ref());
+
+        *self.updater.borrow_mut() = Some(updater);
+    }
+
+    pub fn render(&self, props: &JsValue, state: &JsValue) -> Result<Option<Box<Element>>, JsValue> {
+        Reflect::set(&self.instance, &JsValue::from_str("props"), props)?;
+        Reflect::set(&self.instance, &JsValue::from_str("state"), state)?;
+
+        let render = Reflect::get(&self.instance, &JsValue::from_str("render"))?.unchecked_into::<Function>();
+
+        Ok(render.call0(&self.instance)?
+            .as_f64()
+            .map(|child_ptr| Element::from_ptr(child_ptr as u32 as *mut Element)))
+    }
+
+    /// Calls `componentDidMount` or `componentDidUpdate` once the render is committed
+    pub fn commit(&self) -> Result<(), JsValue> {
+        let props = self.props();
+        let state = Reflect::get(&self.instance, &JsValue::from_str("state"))?;
+        let committed = self.committed.replace(Some((props, state)));
+
+        match committed {
+            Some((prev_props, prev_state)) => {
+                self.call_method("componentDidUpdate", &Array::of2(&prev_props, &prev_state))
+            },
+            None => {
+                self.call_method("componentDidMount", &Array::new())?;
+
+                // Mounting again beneath StrictMode surfaces what `componentWillUnmount` doesn't clean up
+                if cfg!(debug_assertions) && self.is_strict {
+                    self.call_method("componentWillUnmount", &Array::new())?;
+                    self.call_method("componentDidMount", &Array::new())?;
+                }
+
+                Ok(())
+            },
+        }
+    }
+
+    pub fn unmount(&self) -> Result<(), JsValue> {
+        self.call_method("componentWillUnmount", &Array::new())
+    }
+
+    // Lifecycle methods are optional
+    fn call_method(&self, name: &str, args: &Array) -> Result<(), JsValue> {
+        let method = Reflect::get(&self.instance, &JsValue::from_str(name))?;
+
+        if let Some(method) = method.dyn_ref::<Function>() {
+            method.apply(&self.instance, args)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Shallow merge of a `setState` partial state into the previous one, like `Object.assign`
+pub fn merge_state(state: &JsValue, partial_state: &JsValue) -> JsValue {
+    let merged = Object::new();
+
+    for source in [state, partial_state] {
+        if let Some(source) = source.dyn_ref::<Object>() {
+            Object::assign(&merged, source);
+        }
+    }
+
+    merged.into()
+}
@@ -0,0 +1,107 @@
+use std::any::{self, Any};
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::rc::Rc;
+use super::{Context, Element, HookCell, HookKind, HookState, Wakeable, dispatch_state, mount_state, report_hook_misuse, suspend_component};
+
+/// Functional component written in Rust. Its fields are its props, and it renders like a JS
+/// function component would, using hooks from `hooks`.
+pub trait Component: Any {
+    fn render(&self, hooks: &mut Hooks) -> Option<Box<Element>>;
+
+    /// Name diagnostics refer to the component by
+    fn name(&self) -> &'static str {
+        any::type_name::<Self>()
+    }
+}
+
+impl<F> Component for F where F: Fn(&mut Hooks) -> Option<Box<Element>> + 'static {
+    fn render(&self, hooks: &mut Hooks) -> Option<Box<Element>> {
+        self(hooks)
+    }
+}
+
+/// Hooks of the Rust component being rendered, which have to be called in the same order every render
+pub struct Hooks {
+    context_ptr: *mut Context,
+}
+
+impl Hooks {
+    pub(crate) fn new(context_ptr: *mut Context) -> Self {
+        Hooks { context_ptr }
+    }
+
+    pub fn use_state<T: Clone + PartialEq + 'static>(&mut self, initial_value: T) -> (T, SetState<T>) {
+        let initial_value = Rc::new(initial_value);
+        let (state, hook) = mount_state(self.context_ptr, HookKind::State, HookState::Rust(initial_value.clone()));
+
+        let state = match state.downcast_ref::<T>() {
+            Some(state) => state.clone(),
+            None => {
+                report_hook_misuse(self.context_ptr, "called use_state with a different type than in its previous render");
+                (*initial_value).clone()
+            },
+        };
+
+        let set_state = SetState {
+            context_ptr: self.context_ptr,
+            hook,
+            _state: PhantomData,
+        };
+
+        (state, set_state)
+    }
+
+    /// Value kept for as long as the component is mounted, which changing doesn't render it again
+    pub fn use_ref<T: 'static>(&mut self, initial_value: T) -> Rc<RefCell<T>> {
+        let initial_value = Rc::new(RefCell::new(initial_value));
+        let (state, _) = mount_state(self.context_ptr, HookKind::Ref, HookState::Rust(Rc::new(RefBox(Rc::clone(&initial_value)))));
+
+        match state.downcast_ref::<RefBox<T>>() {
+            Some(ref_box) => Rc::clone(&ref_box.0),
+            None => {
+                report_hook_misuse(self.context_ptr, "called use_ref with a different type than in its previous render");
+                initial_value
+            },
+        }
+    }
+
+    /// Suspends the component until `wakeable` settles, like a JS component throwing a promise.
+    /// The closest Suspense boundary renders its fallback meanwhile, and whatever the component
+    /// returns is ignored, so it should return right away.
+    pub fn suspend(&mut self, wakeable: impl Wakeable + 'static) {
+        suspend_component(self.context_ptr, Box::new(wakeable));
+    }
+}
+
+/// State of a `use_ref` hook, the same box in every render
+struct RefBox<T>(Rc<RefCell<T>>);
+
+impl<T> PartialEq for RefBox<T> {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+/// Setter of a state hook, which schedules a render of its component when the value changes
+pub struct SetState<T> {
+    context_ptr: *mut Context,
+    hook: HookCell,
+    _state: PhantomData<T>,
+}
+
+impl<T: PartialEq + 'static> SetState<T> {
+    pub fn set(&self, value: T) {
+        dispatch_state(self.context_ptr, &self.hook, HookState::Rust(Rc::new(value)));
+    }
+}
+
+impl<T> Clone for SetState<T> {
+    fn clone(&self) -> Self {
+        SetState {
+            context_ptr: self.context_ptr,
+            hook: Rc::clone(&self.hook),
+            _state: PhantomData,
+        }
+    }
+}
@@ -1,3 +1,14 @@
 pub static TEXT_ELEMENT: &str = "__TEXT";
 pub static FIBER_ROOT: &str = "_R_";
 pub static FIBER_FUNCTIONAL: &str = "_F_";
+pub static FIBER_CLASS: &str = "_C_";
+pub static FIBER_SUSPENSE: &str = "_S_";
+
+// Remaining idle time (in ms) below which the work loop yields back to the browser
+pub static YIELD_THRESHOLD_MS: f64 = 1.0;
+
+// Time (in ms) the work loop is given by the schedulers that don't report a deadline
+pub static FRAME_BUDGET_MS: f64 = 5.0;
+
+// Size (in bytes) streamed HTML is buffered up to, so small subtrees are sent together
+pub static STREAM_CHUNK_SIZE: usize = 1024;
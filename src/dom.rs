@@ -0,0 +1,208 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{Element as HTMLElement, Text as HTMLText, Document, HtmlInputElement};
+use super::{ElementProps, HostConfig, Node};
+
+pub enum DomNode {
+    Text(HTMLText),
+    Element(HTMLElement),
+}
+
+impl DomNode {
+    fn from_node(node: &Node) -> &DomNode {
+        node.downcast_ref::<DomNode>().expect("node wasn't created by the DOM host")
+    }
+
+    fn as_node(&self) -> &web_sys::Node {
+        match self {
+            DomNode::Text(text) => text,
+            DomNode::Element(element) => element,
+        }
+    }
+
+    /// First node from `node` on that a fiber can be hydrated from, skipping comments
+    /// like the ones separating adjacent text nodes
+    fn hydratable(mut node: Option<web_sys::Node>) -> Option<Node> {
+        while let Some(current) = node {
+            match current.node_type() {
+                web_sys::Node::ELEMENT_NODE => return Some(Node::new(DomNode::Element(current.unchecked_into()))),
+                web_sys::Node::TEXT_NODE => return Some(Node::new(DomNode::Text(current.unchecked_into()))),
+                _ => node = current.next_sibling(),
+            }
+        }
+
+        None
+    }
+}
+
+/// Whether a root's container is the given element
+pub fn is_container(node: &Node, container: &HTMLElement) -> bool {
+    match node.downcast_ref::<DomNode>() {
+        Some(DomNode::Element(element)) => element.is_same_node(Some(container)),
+        _ => false,
+    }
+}
+
+/// Renders into the browser's document
+pub struct DomHost {
+    document: Document,
+}
+
+impl DomHost {
+    pub fn new() -> Self {
+        let document = web_sys::window().unwrap().document().unwrap();
+
+        DomHost { document }
+    }
+
+    fn update_element(&self, element: &HTMLElement, prev_props: Option<&ElementProps>, next_props: &ElementProps) {
+        let prev_class_name = prev_props.and_then(|p| p.class_name());
+
+        if let Some(next) = next_props.class_name().filter(|next| prev_class_name != Some(*next)) {
+            element.set_class_name(next);
+        }
+
+        let input = element.unchecked_ref::<HtmlInputElement>();
+
+        if let (None, Some(next)) = (prev_props.and_then(|p| p.input_type()), next_props.input_type()) {
+            input.set_type(next);
+        }
+
+        // Value and checked are set every time, since the user may have changed them since the last render
+        if let Some(next) = next_props.input_value() {
+            input.set_value(next);
+        }
+
+        if let Some(next) = next_props.input_checked() {
+            input.set_checked(next);
+        }
+
+        let prev_placeholder = prev_props.and_then(|p| p.input_placeholder());
+
+        if let Some(next) = next_props.input_placeholder().filter(|next| prev_placeholder != Some(*next)) {
+            input.set_placeholder(next);
+        }
+
+        update_listener(element, "click", prev_props.and_then(|p| p.on_click()), next_props.on_click());
+        update_listener(element, "change", prev_props.and_then(|p| p.on_change()), next_props.on_change());
+        update_listener(element, "blur", prev_props.and_then(|p| p.on_blur()), next_props.on_blur());
+        update_listener(element, "keydown", prev_props.and_then(|p| p.on_keydown()), next_props.on_keydown());
+    }
+}
+
+impl Default for DomHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HostConfig for DomHost {
+    fn create_instance(&self, element_type: &str, props: &ElementProps) -> Node {
+        let element = self.document.create_element(element_type).unwrap();
+        self.update_element(&element, None, props);
+
+        Node::new(DomNode::Element(element))
+    }
+
+    fn create_text_instance(&self, text: &str) -> Node {
+        Node::new(DomNode::Text(self.document.create_text_node(text)))
+    }
+
+    fn append_child(&self, parent: &Node, child: &Node) -> Result<(), JsValue> {
+        let parent = DomNode::from_node(parent).as_node();
+        parent.append_child(DomNode::from_node(child).as_node())?;
+
+        Ok(())
+    }
+
+    fn insert_before(&self, parent: &Node, child: &Node, before: &Node) -> Result<(), JsValue> {
+        let parent = DomNode::from_node(parent).as_node();
+        parent.insert_before(DomNode::from_node(child).as_node(), Some(DomNode::from_node(before).as_node()))?;
+
+        Ok(())
+    }
+
+    fn remove_child(&self, parent: &Node, child: &Node) -> Result<(), JsValue> {
+        let parent = DomNode::from_node(parent).as_node();
+        parent.remove_child(DomNode::from_node(child).as_node())?;
+
+        Ok(())
+    }
+
+    fn commit_update(&self, instance: &Node, prev_props: Option<&ElementProps>, next_props: &ElementProps) {
+        if let DomNode::Element(element) = DomNode::from_node(instance) {
+            self.update_element(element, prev_props, next_props);
+        }
+    }
+
+    fn commit_text_update(&self, instance: &Node, prev_text: Option<&str>, next_text: &str) {
+        if let DomNode::Text(text) = DomNode::from_node(instance) {
+            if prev_text != Some(next_text) {
+                text.set_node_value(Some(next_text));
+            }
+        }
+    }
+
+    fn first_hydratable_child(&self, parent: &Node) -> Option<Node> {
+        DomNode::hydratable(DomNode::from_node(parent).as_node().first_child())
+    }
+
+    fn next_hydratable_sibling(&self, instance: &Node) -> Option<Node> {
+        DomNode::hydratable(DomNode::from_node(instance).as_node().next_sibling())
+    }
+
+    fn can_hydrate_instance(&self, instance: &Node, element_type: &str, _props: &ElementProps) -> bool {
+        match DomNode::from_node(instance) {
+            DomNode::Element(element) => element.tag_name().eq_ignore_ascii_case(element_type),
+            DomNode::Text(_) => false,
+        }
+    }
+
+    fn can_hydrate_text_instance(&self, instance: &Node, text: &str) -> bool {
+        match DomNode::from_node(instance) {
+            DomNode::Text(node) => node.node_value().as_deref() == Some(text),
+            DomNode::Element(_) => false,
+        }
+    }
+
+    fn commit_hydration(&self, instance: &Node, props: &ElementProps) {
+        if let DomNode::Element(element) = DomNode::from_node(instance) {
+            self.update_element(element, None, props);
+        }
+    }
+
+    fn describe_instance(&self, instance: &Node) -> String {
+        match DomNode::from_node(instance) {
+            DomNode::Element(element) => format!("<{}>", element.tag_name().to_lowercase()),
+            DomNode::Text(text) => format!("\"{}\"", text.node_value().unwrap_or_default()),
+        }
+    }
+
+    fn detach_instance(&self, instance: &Node, props: &ElementProps) {
+        if let DomNode::Element(element) = DomNode::from_node(instance) {
+            update_listener(element, "click", props.on_click(), None);
+            update_listener(element, "change", props.on_change(), None);
+            update_listener(element, "blur", props.on_blur(), None);
+            update_listener(element, "keydown", props.on_keydown(), None);
+        }
+    }
+}
+
+fn update_listener(
+    element: &HTMLElement,
+    event_type: &str,
+    prev_listener: Option<&js_sys::Function>,
+    next_listener: Option<&js_sys::Function>,
+) {
+    if prev_listener == next_listener {
+        return;
+    }
+
+    if let Some(callback) = prev_listener {
+        element.remove_event_listener_with_callback(event_type, callback).unwrap();
+    }
+
+    if let Some(callback) = next_listener {
+        element.add_event_listener_with_callback(event_type, callback).unwrap();
+    }
+}
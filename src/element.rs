@@ -1,19 +1,23 @@
+use std::cell::RefCell;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
-use web_sys::{Element as HTMLElement, Text as HTMLText};
-use super::{TEXT_ELEMENT, FIBER_FUNCTIONAL};
-
-pub enum Node {
-    Text(HTMLText),
-    Element(HTMLElement),
-}
+use wasm_bindgen::JsCast;
+use js_sys::{Object, Reflect};
+use super::{Component, IntoChildren, warn, TEXT_ELEMENT, FIBER_FUNCTIONAL, FIBER_CLASS, FIBER_SUSPENSE};
 
 pub struct Element {
     element_type: String,
     component_function: Option<Rc<js_sys::Function>>,
     component_function_props: Option<Rc<JsValue>>,
+    component: Option<Rc<dyn Component>>,
     props: Option<Box<ElementProps>>,
-    children: Option<Vec<Box<Element>>>,
+    children: Option<Rc<RefCell<Vec<Box<Element>>>>>,
+    memo: Option<Memo>,
+    fallback: Option<Rc<RefCell<Vec<Box<Element>>>>>,
+    key: Option<String>,
+    // Whether the element is an item of a list built at runtime, which needs a key
+    is_list_item: bool,
+    is_strict_mode: bool,
 }
 
 impl Element {
@@ -28,13 +32,66 @@ impl Element {
             element_type,
             component_function,
             component_function_props,
+            component: None,
             props,
-            children,
+            children: children.map(|children| Rc::new(RefCell::new(children))),
+            memo: None,
+            fallback: None,
+            key: None,
+            is_list_item: false,
+            is_strict_mode: false,
+        }
+    }
+
+    /// Starts building a host element from Rust
+    pub fn tag(element_type: &str) -> ElementBuilder {
+        ElementBuilder {
+            element_type: String::from(element_type),
+            props: ElementProps::default(),
+            key: None,
+            children: Vec::new(),
         }
     }
 
+    pub fn text(value: impl Into<String>) -> Box<Element> {
+        Element::from_ptr(create_text_element(value.into()))
+    }
+
+    /// Functional element rendered by a Rust component
+    pub fn from_component(component: impl Component + 'static) -> Element {
+        let mut element = Element::new(String::from(FIBER_FUNCTIONAL), None, None, None, None);
+        element.component = Some(Rc::new(component));
+
+        element
+    }
+
+    /// Functional element without a component, which renders the children it's given
+    pub fn fragment(children: Vec<Box<Element>>) -> Element {
+        Element::new(String::from(FIBER_FUNCTIONAL), None, None, None, Some(children))
+    }
+
+    /// Boundary rendering `fallback` in place of its children while any of them is suspended
+    pub fn suspense(fallback: Vec<Box<Element>>, children: Vec<Box<Element>>) -> Element {
+        let mut element = Element::new(String::from(FIBER_SUSPENSE), None, None, None, Some(children));
+        element.fallback = Some(Rc::new(RefCell::new(fallback)));
+
+        element
+    }
+
+    /// Fragment whose descendants are checked for impure renders in debug builds
+    pub fn strict_mode(children: Vec<Box<Element>>) -> Element {
+        let mut element = Element::fragment(children);
+        element.is_strict_mode = true;
+
+        element
+    }
+
+    pub fn is_strict_mode(&self) -> bool {
+        self.is_strict_mode
+    }
+
     pub fn is_text_element(&self) -> bool {
-        self.element_type == "_T"
+        self.element_type == TEXT_ELEMENT
     }
 
     pub fn element_type(&self) -> &String {
@@ -45,16 +102,12 @@ impl Element {
         &self.props
     }
 
-    pub fn props_mut(&mut self) -> &mut Option<Box<ElementProps>> {
-        &mut self.props
-    }
-
-    pub fn children(&self) -> &Option<Vec<Box<Element>>> {
+    pub fn children(&self) -> &Option<Rc<RefCell<Vec<Box<Element>>>>> {
         &self.children
     }
 
-    pub fn children_mut(&mut self) -> &mut Option<Vec<Box<Element>>> {
-        &mut self.children
+    pub fn fallback(&self) -> Option<&Rc<RefCell<Vec<Box<Element>>>>> {
+        self.fallback.as_ref()
     }
 
     pub fn component_function(&self) -> Option<&Rc<js_sys::Function>> {
@@ -65,12 +118,201 @@ impl Element {
         self.component_function_props.as_ref()
     }
 
+    pub fn component(&self) -> Option<&Rc<dyn Component>> {
+        self.component.as_ref()
+    }
+
+    pub fn memo(&self) -> Option<&Memo> {
+        self.memo.as_ref()
+    }
+
+    pub fn set_memo(&mut self, memo: Option<Memo>) {
+        self.memo = memo;
+    }
+
+    /// Identifies the element among its siblings, so it's only matched with a fiber of the same key
+    pub fn key(&self) -> Option<&String> {
+        self.key.as_ref()
+    }
+
+    pub fn set_key(&mut self, key: Option<String>) {
+        self.key = key;
+    }
+
+    pub fn is_list_item(&self) -> bool {
+        self.is_list_item
+    }
+
+    /// Marks the element as an item of a list, unless it's text which doesn't keep any state
+    pub fn set_list_item(&mut self) {
+        self.is_list_item = !self.is_text_element();
+    }
+
     pub fn from_ptr(ptr: *mut Element) -> Box<Element> {
         unsafe { Box::from_raw(ptr) }
     }
 }
 
-#[derive(Eq)]
+/// Fluent builder for host elements, covering the props `ElementProps` supports
+pub struct ElementBuilder {
+    element_type: String,
+    props: ElementProps,
+    key: Option<String>,
+    children: Vec<Box<Element>>,
+}
+
+impl ElementBuilder {
+    pub fn class(mut self, class_name: impl Into<String>) -> Self {
+        self.props.class_name = Some(class_name.into());
+        self
+    }
+
+    pub fn input_type(mut self, input_type: impl Into<String>) -> Self {
+        self.props.input_type = Some(input_type.into());
+        self
+    }
+
+    pub fn value(mut self, value: impl Into<String>) -> Self {
+        self.props.input_value = Some(value.into());
+        self
+    }
+
+    pub fn placeholder(mut self, placeholder: impl Into<String>) -> Self {
+        self.props.input_placeholder = Some(placeholder.into());
+        self
+    }
+
+    pub fn checked(mut self, checked: bool) -> Self {
+        self.props.input_checked = Some(checked);
+        self
+    }
+
+    /// Sets a string attribute by its HTML name, warning about and ignoring ones elements don't support
+    pub fn attr(self, name: &str, value: impl Into<String>) -> Self {
+        match name {
+            "class" => self.class(value),
+            "type" => self.input_type(value),
+            "value" => self.value(value),
+            "placeholder" => self.placeholder(value),
+            _ => {
+                warn(&format!("<{}> doesn't support the '{}' attribute, so it is ignored", self.element_type, name));
+                self
+            },
+        }
+    }
+
+    /// Listens to `event_type`, one of "click", "change", "blur" or "keydown", warning about and ignoring others
+    pub fn on(mut self, event_type: &str, listener: impl Into<js_sys::Function>) -> Self {
+        let listener = Some(listener.into());
+
+        match event_type {
+            "click" => self.props.on_click = listener,
+            "change" => self.props.on_change = listener,
+            "blur" => self.props.on_blur = listener,
+            "keydown" => self.props.on_keydown = listener,
+            _ => warn(&format!("<{}> doesn't support '{}' listeners, so the listener is ignored", self.element_type, event_type)),
+        }
+
+        self
+    }
+
+    pub fn key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+
+    pub fn child(mut self, child: impl IntoChildren) -> Self {
+        child.into_children(&mut self.children);
+        self
+    }
+
+    /// Adds the items of a list, which need a key to be told apart
+    pub fn children<I>(mut self, children: I) -> Self where I: IntoIterator, I::Item: IntoChildren {
+        let start = self.children.len();
+
+        for child in children {
+            child.into_children(&mut self.children);
+        }
+
+        for child in &mut self.children[start..] {
+            child.set_list_item();
+        }
+
+        self
+    }
+
+    pub fn build(self) -> Box<Element> {
+        let mut element = Element::new(
+            self.element_type,
+            None,
+            None,
+            Some(Box::new(self.props)),
+            Some(self.children)
+        );
+
+        element.set_key(self.key);
+
+        Box::new(element)
+    }
+}
+
+impl IntoChildren for ElementBuilder {
+    fn into_children(self, children: &mut Vec<Box<Element>>) {
+        children.push(self.build());
+    }
+}
+
+/// How a memoized functional component decides that its props didn't change
+#[derive(Clone)]
+pub enum Memo {
+    Shallow,
+    Custom(Rc<js_sys::Function>),
+}
+
+impl Memo {
+    pub fn are_props_equal(&self, prev_props: &JsValue, next_props: &JsValue) -> bool {
+        match self {
+            Memo::Shallow => shallow_equal(prev_props, next_props),
+            Memo::Custom(compare) => {
+                compare.call2(&JsValue::null(), prev_props, next_props)
+                    .is_ok_and(|equal| equal.is_truthy())
+            }
+        }
+    }
+}
+
+/// Whether JS props carry element handles in `children`, which the render they're passed to consumes
+pub fn has_element_children(props: &JsValue) -> bool {
+    Reflect::has(props, &JsValue::from_str("children")).unwrap_or(false)
+}
+
+fn shallow_equal(prev: &JsValue, next: &JsValue) -> bool {
+    if Object::is(prev, next) {
+        return true;
+    }
+
+    let (prev, next) = match (prev.dyn_ref::<Object>(), next.dyn_ref::<Object>()) {
+        (Some(prev), Some(next)) => (prev, next),
+        (_, _) => return false,
+    };
+
+    let prev_keys = Object::keys(prev);
+
+    if prev_keys.length() != Object::keys(next).length() {
+        return false;
+    }
+
+    prev_keys.iter().all(|key| {
+        let has_key = Reflect::has(next, &key).unwrap_or(false);
+
+        has_key && Object::is(
+            &Reflect::get(prev, &key).unwrap_or(JsValue::UNDEFINED),
+            &Reflect::get(next, &key).unwrap_or(JsValue::UNDEFINED)
+        )
+    })
+}
+
+#[derive(Clone, Default, Eq)]
 pub struct ElementProps {
     class_name: Option<String>,
     node_value: Option<String>,
@@ -146,11 +388,11 @@ impl PartialEq for ElementProps {
     }
 }
 
-#[wasm_bindgen]
+#[wasm_bindgen(unchecked_return_type = "ElementHandle")]
 pub fn create_element(
     element_type: String,
-    props_ptr: *mut ElementProps,
-    children_ptr: &[u32]
+    #[wasm_bindgen(unchecked_param_type = "PropsHandle")] props_ptr: *mut ElementProps,
+    #[wasm_bindgen(unchecked_param_type = "ElementHandle[]")] children_ptr: &[u32]
 ) -> *mut Element {
     let props = ElementProps::from_ptr(props_ptr);
 
@@ -169,7 +411,7 @@ pub fn create_element(
     Box::into_raw(Box::new(element))
 }
 
-#[wasm_bindgen]
+#[wasm_bindgen(unchecked_return_type = "ElementHandle")]
 pub fn create_text_element(value: String) -> *mut Element {
     let props = ElementProps {
         class_name: None,
@@ -195,7 +437,7 @@ pub fn create_text_element(value: String) -> *mut Element {
     Box::into_raw(Box::new(element))
 }
 
-#[wasm_bindgen]
+#[wasm_bindgen(unchecked_return_type = "ElementHandle")]
 pub fn create_functional_component(func: js_sys::Function, props: JsValue) -> *mut Element {
     let element = Element::new(
         String::from(FIBER_FUNCTIONAL),
@@ -208,7 +450,99 @@ pub fn create_functional_component(func: js_sys::Function, props: JsValue) -> *m
     Box::into_raw(Box::new(element))
 }
 
-#[wasm_bindgen]
+#[wasm_bindgen(unchecked_return_type = "ElementHandle")]
+pub fn create_strict_mode(
+    #[wasm_bindgen(unchecked_param_type = "ElementHandle[]")] children_ptr: &[u32]
+) -> *mut Element {
+    let children = children_ptr.iter()
+        .map(|ptr| Element::from_ptr(*ptr as *mut Element))
+        .collect::<Vec<Box<Element>>>();
+
+    Box::into_raw(Box::new(Element::strict_mode(children)))
+}
+
+/// Marks an element as an item of an array of children
+#[wasm_bindgen(unchecked_return_type = "ElementHandle")]
+pub fn mark_list_item(
+    #[wasm_bindgen(unchecked_param_type = "ElementHandle")] element_ptr: *mut Element
+) -> *mut Element {
+    let mut element = Element::from_ptr(element_ptr);
+
+    element.set_list_item();
+
+    Box::into_raw(element)
+}
+
+#[wasm_bindgen(unchecked_return_type = "ElementHandle")]
+pub fn create_suspense(
+    #[wasm_bindgen(unchecked_param_type = "ElementHandle[]")] fallback_ptr: &[u32],
+    #[wasm_bindgen(unchecked_param_type = "ElementHandle[]")] children_ptr: &[u32]
+) -> *mut Element {
+    let to_elements = |ptrs: &[u32]| ptrs.iter()
+        .map(|ptr| Element::from_ptr(*ptr as *mut Element))
+        .collect::<Vec<Box<Element>>>();
+
+    Box::into_raw(Box::new(Element::suspense(to_elements(fallback_ptr), to_elements(children_ptr))))
+}
+
+/// Element of a JS class component, which is instantiated once when it's mounted
+#[wasm_bindgen(unchecked_return_type = "ElementHandle")]
+pub fn create_class_component(class: js_sys::Function, props: JsValue) -> *mut Element {
+    let element = Element::new(
+        String::from(FIBER_CLASS),
+        Some(Rc::new(class)),
+        Some(Rc::new(props)),
+        None,
+        None
+    );
+
+    Box::into_raw(Box::new(element))
+}
+
+#[wasm_bindgen(unchecked_return_type = "ElementHandle")]
+pub fn create_fragment(
+    #[wasm_bindgen(unchecked_param_type = "ElementHandle[]")] children_ptr: &[u32]
+) -> *mut Element {
+    let children = children_ptr.iter()
+        .map(|ptr| Element::from_ptr(*ptr as *mut Element))
+        .collect::<Vec<Box<Element>>>();
+
+    Box::into_raw(Box::new(Element::fragment(children)))
+}
+
+/// Sets the key the automatic JSX runtime passes apart from the props
+#[wasm_bindgen(unchecked_return_type = "ElementHandle")]
+pub fn set_key(
+    #[wasm_bindgen(unchecked_param_type = "ElementHandle")] element_ptr: *mut Element,
+    key: Option<String>
+) -> *mut Element {
+    let mut element = Element::from_ptr(element_ptr);
+
+    element.set_key(key);
+
+    Box::into_raw(element)
+}
+
+/// Marks a functional element so it skips rendering while its props stay the same, compared
+/// shallowly or by `compare(prev_props, next_props)`
+#[wasm_bindgen(unchecked_return_type = "ElementHandle")]
+pub fn memo(
+    #[wasm_bindgen(unchecked_param_type = "ElementHandle")] element_ptr: *mut Element,
+    compare: Option<js_sys::Function>
+) -> *mut Element {
+    let mut element = Element::from_ptr(element_ptr);
+
+    let memo = match compare {
+        Some(compare) => Memo::Custom(Rc::new(compare)),
+        None => Memo::Shallow,
+    };
+
+    element.set_memo(Some(memo));
+
+    Box::into_raw(element)
+}
+
+#[wasm_bindgen(unchecked_return_type = "PropsHandle")]
 pub fn create_props(
     class_name: Option<String>,
     node_value: Option<String>,
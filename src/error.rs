@@ -0,0 +1,34 @@
+use std::fmt;
+use wasm_bindgen::prelude::*;
+
+/// Why a render or a commit stopped, which is thrown to JS
+pub enum Error {
+    /// Reactron used the wrong way, like hooks called in a different order than in the previous render
+    Invalid(String),
+    /// Value thrown by a component, a lifecycle method or the host, thrown on as it is
+    Js(JsValue),
+}
+
+impl From<JsValue> for Error {
+    fn from(value: JsValue) -> Self {
+        Error::Js(value)
+    }
+}
+
+impl From<Error> for JsValue {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Invalid(message) => js_sys::Error::new(&format!("Reactron: {}", message)).into(),
+            Error::Js(value) => value,
+        }
+    }
+}
+
+impl fmt::Debug for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Invalid(message) => write!(f, "Reactron: {}", message),
+            Error::Js(value) => write!(f, "{:?}", value),
+        }
+    }
+}
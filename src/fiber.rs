@@ -1,7 +1,7 @@
 use wasm_bindgen::prelude::*;
 use std::cell::RefCell;
 use std::rc::Rc;
-use super::{Element, ElementProps, Node, TEXT_ELEMENT, FIBER_ROOT, FIBER_FUNCTIONAL};
+use super::{ClassInstance, Component, Element, SuspenseBoundary, ElementProps, Memo, Node, HookCell, Lane, Lanes, TEXT_ELEMENT, FIBER_ROOT, FIBER_FUNCTIONAL, FIBER_CLASS, FIBER_SUSPENSE};
 
 pub type FiberCell = Rc<RefCell<Box<Fiber>>>;
 
@@ -15,13 +15,30 @@ pub struct Fiber {
     sibling: Option<FiberCell>,
     child: Option<FiberCell>,
     effect_tag: Option<FiberEffect>,
+    key: Option<String>,
+    // Whether the fiber is beneath a StrictMode element
+    is_strict: bool,
+    // Lanes of the updates waiting to be rendered on this fiber
+    lanes: Lanes,
+    // ...and anywhere below it
+    child_lanes: Lanes,
 
     // Functional
     component_function: Option<Rc<js_sys::Function>>,
     component_function_props: Option<Rc<JsValue>>,
+    component: Option<Rc<dyn Component>>,
+    class_instance: Option<Rc<ClassInstance>>,
+    memo: Option<Memo>,
+
+    // Suspense
+    fallback: Option<Rc<RefCell<Vec<Box<Element>>>>>,
+    suspense: Option<Rc<SuspenseBoundary>>,
+    // Whether this render of the boundary shows the fallback, as something below it suspended.
+    // Every render tries the children first, so it isn't carried over to the next one.
+    is_suspended: bool,
 
     // Hooks
-    hooks: Option<Vec<Rc<RefCell<JsValue>>>>,
+    hooks: Option<Vec<HookCell>>,
     hook_idx: u32,
 }
 
@@ -37,13 +54,46 @@ impl Fiber {
             sibling: None,
             child: None,
             effect_tag: None,
+            key: None,
+            is_strict: false,
+            lanes: Lanes::default(),
+            child_lanes: Lanes::default(),
             component_function: None,
             component_function_props: None,
+            component: None,
+            class_instance: None,
+            memo: None,
+            fallback: None,
+            suspense: None,
+            is_suspended: false,
             hooks: None,
             hook_idx: 0u32,
         }
     }
 
+    /// Copy of a current fiber for a render that doesn't change it
+    pub fn reuse(current: &Fiber) -> Self {
+        let mut fiber = Self::new(&current._type);
+
+        fiber.props = current.props.clone();
+        fiber.element_children = current.element_children.clone();
+        fiber.dom_node = current.dom_node.clone();
+        fiber.key = current.key.clone();
+        fiber.is_strict = current.is_strict;
+        fiber.lanes = current.lanes;
+        fiber.child_lanes = current.child_lanes;
+        fiber.component_function = current.component_function.clone();
+        fiber.component_function_props = current.component_function_props.clone();
+        fiber.component = current.component.clone();
+        fiber.class_instance = current.class_instance.clone();
+        fiber.fallback = current.fallback.clone();
+        fiber.suspense = current.suspense.clone();
+        fiber.memo = current.memo.clone();
+        fiber.hooks = current.hooks.clone();
+
+        fiber
+    }
+
     pub fn new_root() -> Self {
         Self::new(FIBER_ROOT)
     }
@@ -52,8 +102,22 @@ impl Fiber {
         &self._type
     }
 
+    /// Fiber rendered by a component, which has no instance of its own
     pub fn is_functional_tree(&self) -> bool {
-        &self._type == FIBER_FUNCTIONAL
+        &self._type == FIBER_FUNCTIONAL || self.is_class_component() || self.is_suspense_boundary()
+    }
+
+    pub fn is_suspense_boundary(&self) -> bool {
+        &self._type == FIBER_SUSPENSE
+    }
+
+    pub fn is_class_component(&self) -> bool {
+        &self._type == FIBER_CLASS
+    }
+
+    /// Functional fiber with neither a Rust nor a JS component to render
+    pub fn is_fragment(&self) -> bool {
+        self.is_functional_tree() && self.component.is_none() && self.component_function.is_none()
     }
 
     pub fn is_text_fiber(&self) -> bool {
@@ -108,6 +172,15 @@ impl Fiber {
         self.alternate.replace(alternate);
     }
 
+    /// Children to reconcile, which are the fallback of a suspended boundary
+    pub fn rendered_children(&self) -> Option<&Rc<RefCell<Vec<Box<Element>>>>> {
+        if self.is_suspended {
+            self.fallback.as_ref()
+        } else {
+            self.element_children.as_ref()
+        }
+    }
+
     pub fn element_children(&self) -> &Option<Rc<RefCell<Vec<Box<Element>>>>> {
         &self.element_children
     }
@@ -124,6 +197,79 @@ impl Fiber {
         self.effect_tag.replace(effect);
     }
 
+    pub fn clear_effect_tag(&mut self) {
+        self.effect_tag = None;
+    }
+
+    pub fn lanes(&self) -> Lanes {
+        self.lanes
+    }
+
+    pub fn set_lanes(&mut self, lanes: Lanes) {
+        self.lanes = lanes;
+    }
+
+    pub fn mark_lane(&mut self, lane: Lane) {
+        self.lanes.insert(lane);
+    }
+
+    pub fn child_lanes(&self) -> Lanes {
+        self.child_lanes
+    }
+
+    pub fn set_child_lanes(&mut self, lanes: Lanes) {
+        self.child_lanes = lanes;
+    }
+
+    pub fn mark_child_lane(&mut self, lane: Lane) {
+        self.child_lanes.insert(lane);
+    }
+
+    /// Whether rendering this fiber would give the same children as its alternate did: same
+    /// component and props object, or the very same elements, with no update for this render
+    pub fn can_bail_out(&self, render_lane: Lane) -> bool {
+        let alternate = match self.alternate() {
+            Some(alternate) => alternate.borrow(),
+            None => return false,
+        };
+
+        if self.lanes.has_included_in(render_lane) {
+            return false;
+        }
+
+        // Fragments compare their children, as host fibers do
+        if self.is_functional_tree() && !self.is_fragment() {
+            // A Rust component is its own props
+            if let (Some(component), Some(old_component)) = (self.component(), alternate.component()) {
+                return Rc::ptr_eq(component, old_component);
+            }
+
+            let same_props = match (self.component_function_props(), alternate.component_function_props()) {
+                (Some(props), Some(old_props)) => {
+                    Rc::ptr_eq(props, old_props) || self.memo().is_some_and(|memo| {
+                        memo.are_props_equal(old_props, props)
+                    })
+                },
+                (_, _) => false,
+            };
+
+            // Elements hold their own handle to the function, so a memoized component
+            // rendered again by its parent has to be compared on the JS side
+            let same_function = same_props && match (self.component_function(), alternate.component_function()) {
+                (Some(func), Some(old_func)) => Rc::ptr_eq(func, old_func) || func == old_func,
+                (_, _) => false,
+            };
+
+            same_function
+        } else {
+            match (self.element_children(), alternate.element_children()) {
+                (Some(children), Some(old_children)) => Rc::ptr_eq(children, old_children),
+                (None, None) => true,
+                (_, _) => false,
+            }
+        }
+    }
+
     pub fn has_props_changed(&self, other_props: &Box<ElementProps>) -> bool {
         if let Some(props) = self.props() {
             !(props == other_props)
@@ -148,13 +294,99 @@ impl Fiber {
         self.component_function_props = props;
     }
 
-    pub fn add_hook(&mut self, hook: Rc<RefCell<JsValue>>) {
+    pub fn key(&self) -> Option<&String> {
+        self.key.as_ref()
+    }
+
+    pub fn set_key(&mut self, key: Option<String>) {
+        self.key = key;
+    }
+
+    pub fn set_fallback(&mut self, fallback: Option<Rc<RefCell<Vec<Box<Element>>>>>) {
+        self.fallback = fallback;
+    }
+
+    pub fn suspense(&self) -> Option<&Rc<SuspenseBoundary>> {
+        self.suspense.as_ref()
+    }
+
+    pub fn set_suspense(&mut self, suspense: Option<Rc<SuspenseBoundary>>) {
+        self.suspense = suspense;
+    }
+
+    pub fn is_suspended(&self) -> bool {
+        self.is_suspended
+    }
+
+    pub fn set_suspended(&mut self, is_suspended: bool) {
+        self.is_suspended = is_suspended;
+    }
+
+    pub fn class_instance(&self) -> Option<&Rc<ClassInstance>> {
+        self.class_instance.as_ref()
+    }
+
+    pub fn set_class_instance(&mut self, instance: Option<Rc<ClassInstance>>) {
+        self.class_instance = instance;
+    }
+
+    pub fn is_strict(&self) -> bool {
+        self.is_strict
+    }
+
+    pub fn set_strict(&mut self, is_strict: bool) {
+        self.is_strict = is_strict;
+    }
+
+    /// Whether `element` is rendered by the same component as this fiber, whose hooks and
+    /// class instance it can then take over
+    pub fn is_same_component(&self, element: &Element) -> bool {
+        match (self.component(), element.component()) {
+            (Some(component), Some(other)) => (**component).type_id() == (**other).type_id(),
+            (None, None) => match (self.component_function(), element.component_function()) {
+                (Some(func), Some(other)) => Rc::ptr_eq(func, other) || **func == **other,
+                // Fragments
+                (None, None) => true,
+                (_, _) => false,
+            },
+            (_, _) => false,
+        }
+    }
+
+    /// Name of the component rendering the fiber, for diagnostics
+    pub fn component_name(&self) -> String {
+        let name = match (self.component(), self.component_function()) {
+            (Some(component), _) => String::from(component.name()),
+            (None, Some(func)) => String::from(func.name()),
+            (None, None) => String::new(),
+        };
+
+        if name.is_empty() { String::from("Anonymous component") } else { name }
+    }
+
+    pub fn component(&self) -> Option<&Rc<dyn Component>> {
+        self.component.as_ref()
+    }
+
+    pub fn set_component(&mut self, component: Option<Rc<dyn Component>>) {
+        self.component = component;
+    }
+
+    pub fn memo(&self) -> Option<&Memo> {
+        self.memo.as_ref()
+    }
+
+    pub fn set_memo(&mut self, memo: Option<Memo>) {
+        self.memo = memo;
+    }
+
+    pub fn add_hook(&mut self, hook: HookCell) {
         if let Some(hooks) = &mut self.hooks {
             hooks.push(hook);
         }
     }
 
-    pub fn get_hook_at(&self, pos: usize) -> Option<Rc<RefCell<JsValue>>> {
+    pub fn get_hook_at(&self, pos: usize) -> Option<HookCell> {
         self.hooks.as_ref().map_or(None, |hooks| {
             hooks.get(pos).map_or(None, |hook| {
                 Some(Rc::clone(hook))
@@ -162,6 +394,14 @@ impl Fiber {
         })
     }
 
+    pub fn hook_count(&self) -> usize {
+        self.hooks.as_ref().map_or(0, Vec::len)
+    }
+
+    pub fn hooks(&self) -> Option<&Vec<HookCell>> {
+        self.hooks.as_ref()
+    }
+
     pub fn hook_idx(&self) -> u32 {
         self.hook_idx
     }
@@ -170,9 +410,153 @@ impl Fiber {
         self.hook_idx += 1;
     }
 
-    pub fn set_hooks(&mut self, hooks: Option<Vec<Rc<RefCell<JsValue>>>>) {
+    pub fn set_hooks(&mut self, hooks: Option<Vec<HookCell>>) {
         self.hooks = hooks;
     }
+
+    /// Starts the hook list over, for a render that adds every hook again
+    pub fn reset_hooks(&mut self) {
+        self.hooks = Some(Vec::new());
+        self.hook_idx = 0;
+    }
+}
+
+/// Marks an update on `fiber` and flags its parents as having work below them. Both trees
+/// are marked since the fiber's last render may or may not have been committed.
+pub fn mark_update_lane(fiber: &FiberCell, lane: Lane) {
+    {
+        let mut fiber = fiber.borrow_mut();
+        fiber.mark_lane(lane);
+
+        if let Some(alternate) = fiber.alternate() {
+            alternate.borrow_mut().mark_lane(lane);
+        }
+    }
+
+    for parent in fiber.parents() {
+        let mut parent = parent.borrow_mut();
+        parent.mark_child_lane(lane);
+
+        if let Some(alternate) = parent.alternate() {
+            alternate.borrow_mut().mark_child_lane(lane);
+        }
+    }
+}
+
+/// Breaks the links between the fibers of a tree, and of the trees it was rendered from,
+/// since parents and children keep each other alive
+pub fn release_fiber_tree(fiber: FiberCell) {
+    let mut fibers = vec![fiber];
+
+    while let Some(fiber) = fibers.pop() {
+        let mut fiber = fiber.borrow_mut();
+
+        fibers.extend(fiber.child.take());
+        fibers.extend(fiber.sibling.take());
+        fibers.extend(fiber.alternate.take());
+
+        fiber.parent = None;
+        fiber.hooks = None;
+        fiber.dom_node = None;
+        fiber.element_children = None;
+    }
+}
+
+/// Instance of the closest parent that has one, which is where the fiber's instances go
+pub fn host_parent(fiber: &FiberCell) -> Option<Rc<RefCell<Node>>> {
+    fiber.parents().find_map(|parent| parent.borrow().dom_node().map(Rc::clone))
+}
+
+/// Instance of the first committed host fiber after `fiber` under the same host parent,
+/// going through functional fibers on the way
+pub fn host_sibling(fiber: &FiberCell) -> Option<Rc<RefCell<Node>>> {
+    let mut node = Rc::clone(fiber);
+
+    'siblings: loop {
+        // Go up through the functional parents until one of them has a sibling
+        loop {
+            let sibling = node.borrow().sibling().as_ref().map(Rc::clone);
+
+            if let Some(sibling) = sibling {
+                node = sibling;
+                break;
+            }
+
+            let parent = node.borrow().parent().as_ref().map(Rc::clone)?;
+
+            if parent.borrow().dom_node().is_some() {
+                return None;
+            }
+
+            node = parent;
+        }
+
+        // Then down to its first host fiber, unless it isn't placed yet
+        loop {
+            let child = {
+                let fiber = node.borrow();
+
+                if let Some(FiberEffect::Placement) = fiber.effect_tag() {
+                    continue 'siblings;
+                }
+
+                if let Some(dom_node) = fiber.dom_node() {
+                    return Some(Rc::clone(dom_node));
+                }
+
+                match fiber.child() {
+                    Some(child) => Rc::clone(child),
+                    None => continue 'siblings,
+                }
+            };
+
+            node = child;
+        }
+    }
+}
+
+/// Instances of the topmost host fibers of a subtree, in the order they're in
+pub fn host_instances(fiber: &FiberCell) -> Vec<Rc<RefCell<Node>>> {
+    let mut instances = Vec::new();
+    let mut fibers = vec![Rc::clone(fiber)];
+
+    while let Some(fiber) = fibers.pop() {
+        let fiber = fiber.borrow();
+
+        if let Some(dom_node) = fiber.dom_node() {
+            instances.push(Rc::clone(dom_node));
+            continue;
+        }
+
+        let mut children = Vec::new();
+        let mut child_fiber = fiber.child().as_ref().map(Rc::clone);
+
+        while let Some(child) = child_fiber {
+            child_fiber = child.borrow().sibling().as_ref().map(Rc::clone);
+            children.push(child);
+        }
+
+        // The first child is taken off the stack first
+        fibers.extend(children.into_iter().rev());
+    }
+
+    instances
+}
+
+/// Where a child is matched with the children of the previous render, by its key or else its index
+#[derive(PartialEq, Eq, Hash)]
+pub enum ChildSlot {
+    Key(String),
+    Index(usize),
+}
+
+impl ChildSlot {
+    pub fn new(key: Option<&String>, index: usize) -> Self {
+        match key {
+            Some(key) => ChildSlot::Key(key.clone()),
+            None => ChildSlot::Index(index),
+        }
+    }
 }
 
 pub trait FiberParentIterator {
@@ -213,4 +597,6 @@ pub enum FiberEffect {
     Placement,
     Update,
     Deletion,
+    // The instance was claimed from existing ones
+    Hydration,
 }
@@ -0,0 +1,165 @@
+use wasm_bindgen::prelude::*;
+use std::any::Any;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::{Rc, Weak};
+use super::{Fiber, FiberCell, Lane, Lanes, Root, RootCell};
+
+pub type HookCell = Rc<RefCell<Hook>>;
+
+/// Value of a state hook, from a JS component or a Rust one
+#[derive(Clone)]
+pub enum HookState {
+    Js(JsValue),
+    Rust(Rc<dyn RustState>),
+}
+
+impl HookState {
+    /// Compared like React does, with `Object.is` for JS values
+    fn is_same(&self, other: &HookState) -> bool {
+        match (self, other) {
+            (HookState::Js(value), HookState::Js(other)) => js_sys::Object::is(value, other),
+            (HookState::Rust(value), HookState::Rust(other)) => value.equals(other.as_any()),
+            (_, _) => false,
+        }
+    }
+
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        match self {
+            HookState::Rust(value) => value.as_any().downcast_ref::<T>(),
+            HookState::Js(_) => None,
+        }
+    }
+}
+
+pub trait RustState {
+    fn as_any(&self) -> &dyn Any;
+
+    fn equals(&self, other: &dyn Any) -> bool;
+}
+
+impl<T: PartialEq + 'static> RustState for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn equals(&self, other: &dyn Any) -> bool {
+        other.downcast_ref::<T>().is_some_and(|other| self == other)
+    }
+}
+
+/// Hook a slot was created by, which has to be the same in every render of its fiber
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HookKind {
+    State,
+    Ref,
+}
+
+impl fmt::Display for HookKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HookKind::State => write!(f, "useState"),
+            HookKind::Ref => write!(f, "useRef"),
+        }
+    }
+}
+
+/// State hook shared by a functional fiber and all of its alternates
+pub struct Hook {
+    kind: HookKind,
+    // State of the last committed render, which queued updates are applied on
+    state: HookState,
+    queue: Vec<Update>,
+    // Fiber of the latest render, kept weak since the fiber owns its hooks
+    fiber: Option<Weak<RefCell<Box<Fiber>>>>,
+    root: Option<Weak<RefCell<Root>>>,
+}
+
+struct Update {
+    lane: Lane,
+    value: HookState,
+}
+
+impl Hook {
+    pub fn new(kind: HookKind, state: HookState) -> Self {
+        Hook {
+            kind,
+            state,
+            queue: Vec::new(),
+            fiber: None,
+            root: None,
+        }
+    }
+
+    pub fn kind(&self) -> HookKind {
+        self.kind
+    }
+
+    pub fn enqueue(&mut self, lane: Lane, value: HookState) {
+        self.queue.push(Update { lane, value });
+    }
+
+    pub fn fiber(&self) -> Option<FiberCell> {
+        self.fiber.as_ref().and_then(Weak::upgrade)
+    }
+
+    pub fn set_fiber(&mut self, fiber: &FiberCell) {
+        self.fiber = Some(Rc::downgrade(fiber));
+    }
+
+    pub fn root(&self) -> Option<RootCell> {
+        self.root.as_ref().and_then(Weak::upgrade)
+    }
+
+    pub fn set_root(&mut self, root: &RootCell) {
+        self.root = Some(Rc::downgrade(root));
+    }
+
+    /// Whether `value` is the state already, with no update that could still change it
+    pub fn is_current_state(&self, value: &HookState) -> bool {
+        self.queue.is_empty() && self.state.is_same(value)
+    }
+
+    /// State once every queued update is applied, which class components merge partial states into
+    pub fn latest_state(&self) -> HookState {
+        self.queue.last().map_or_else(|| self.state.clone(), |update| update.value.clone())
+    }
+
+    /// State as seen by a render of `render_lane`. The queue is left untouched so an abandoned
+    /// render doesn't lose updates; the returned length has to be handed back to `commit`.
+    pub fn render_state(&self, render_lane: Lane) -> (HookState, usize) {
+        let state = self.queue.iter()
+            .rev()
+            .find(|update| update.lane.is_included_in(render_lane))
+            .map_or_else(|| self.state.clone(), |update| update.value.clone());
+
+        (state, self.queue.len())
+    }
+
+    /// Lanes of the updates a render of `render_lane` leaves in the queue
+    pub fn skipped_lanes(&self, render_lane: Lane) -> Lanes {
+        let mut lanes = Lanes::default();
+
+        for update in self.queue.iter().filter(|update| !update.lane.is_included_in(render_lane)) {
+            lanes.insert(update.lane);
+        }
+
+        lanes
+    }
+
+    /// Drops the updates applied by a committed render of `render_lane`. Updates queued after a
+    /// skipped one are kept so they are applied again, in order, on top of the skipped update.
+    pub fn commit(&mut self, render_lane: Lane, rendered_len: usize) {
+        let mut remaining = Vec::new();
+
+        for (i, update) in self.queue.drain(..).enumerate() {
+            if i < rendered_len && update.lane.is_included_in(render_lane) && remaining.is_empty() {
+                self.state = update.value;
+            } else {
+                remaining.push(update);
+            }
+        }
+
+        self.queue = remaining;
+    }
+}
@@ -0,0 +1,73 @@
+use wasm_bindgen::prelude::*;
+use std::any::Any;
+use super::ElementProps;
+
+/// Instance created by a host config for a host or text fiber
+pub struct Node(Box<dyn Any>);
+
+impl Node {
+    pub fn new<T: 'static>(instance: T) -> Self {
+        Node(Box::new(instance))
+    }
+
+    pub fn downcast_ref<T: 'static>(&self) -> Option<&T> {
+        self.0.downcast_ref::<T>()
+    }
+}
+
+/// Everything the reconciler needs from the target it renders to. Instances are created while
+/// rendering, but only attached to, moved in or removed from their parents when committing.
+pub trait HostConfig {
+    fn create_instance(&self, element_type: &str, props: &ElementProps) -> Node;
+
+    fn create_text_instance(&self, text: &str) -> Node;
+
+    fn append_child(&self, parent: &Node, child: &Node) -> Result<(), JsValue>;
+
+    fn insert_before(&self, parent: &Node, child: &Node, before: &Node) -> Result<(), JsValue>;
+
+    fn remove_child(&self, parent: &Node, child: &Node) -> Result<(), JsValue>;
+
+    /// Called once everything below a host instance is rendered, before any of it is committed
+    fn complete_instance(&self, _instance: &Node) {}
+
+    fn commit_update(&self, instance: &Node, prev_props: Option<&ElementProps>, next_props: &ElementProps);
+
+    fn commit_text_update(&self, instance: &Node, prev_text: Option<&str>, next_text: &str);
+
+    /// First child of an existing instance, which a fiber can claim when hydrating
+    fn first_hydratable_child(&self, _parent: &Node) -> Option<Node> {
+        None
+    }
+
+    fn next_hydratable_sibling(&self, _instance: &Node) -> Option<Node> {
+        None
+    }
+
+    /// Whether an existing instance was rendered from an element of this type. Props that differ
+    /// are set again when the hydration is committed.
+    fn can_hydrate_instance(&self, _instance: &Node, _element_type: &str, _props: &ElementProps) -> bool {
+        false
+    }
+
+    fn can_hydrate_text_instance(&self, _instance: &Node, _text: &str) -> bool {
+        false
+    }
+
+    /// Sets up a claimed instance for what can't be rendered beforehand, like listeners
+    fn commit_hydration(&self, _instance: &Node, _props: &ElementProps) {}
+
+    /// Short description of an instance for warnings
+    fn describe_instance(&self, _instance: &Node) -> String {
+        String::from("node")
+    }
+
+    /// Called for every instance of a deleted subtree, to release what it registered outside of the tree
+    fn detach_instance(&self, _instance: &Node, _props: &ElementProps) {}
+
+    /// Whether the tree is rendered once and never updated, as on a server. Suspended boundaries
+    /// keep their fallback there, instead of rendering their children again later.
+    fn is_static(&self) -> bool {
+        false
+    }
+}
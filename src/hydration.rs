@@ -0,0 +1,106 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use super::{Fiber, HostConfig, Node, warn};
+
+/// Progress of a render that takes over instances rendered beforehand, usually by a server,
+/// instead of creating them. Host fibers claim the existing instances in the order they begin.
+pub struct Hydration {
+    // Next instance a host fiber can claim
+    cursor: Option<Node>,
+    // Whether the fibers being rendered can claim instances, which they can't below a mismatch
+    is_claiming: bool,
+    // Cursor and claiming state to restore once each host fiber being rendered completes
+    parents: Vec<(Option<Node>, bool)>,
+    // Existing instances that no fiber could claim
+    deletions: Vec<(Rc<RefCell<Node>>, Node)>,
+}
+
+impl Hydration {
+    pub fn new(container: &Node, host: &dyn HostConfig) -> Self {
+        Hydration {
+            cursor: host.first_hydratable_child(container),
+            is_claiming: true,
+            parents: Vec::new(),
+            deletions: Vec::new(),
+        }
+    }
+
+    /// Takes the next existing instance for a host fiber that begins rendering, if it was
+    /// rendered from the same element. Otherwise the instance is deleted and the fiber's
+    /// whole subtree is created again.
+    pub fn claim(&mut self, host: &dyn HostConfig, fiber: &Fiber, parent_instance: Option<Rc<RefCell<Node>>>) -> Option<Node> {
+        let candidate = self.cursor.take();
+        let next_sibling = candidate.as_ref().and_then(|candidate| host.next_hydratable_sibling(candidate));
+
+        self.parents.push((next_sibling, self.is_claiming));
+
+        if !self.is_claiming {
+            return None;
+        }
+
+        let props = fiber.props()?;
+        let text = if fiber.is_text_fiber() { props.node_value() } else { None };
+        let expected = match text {
+            Some(text) => format!("\"{}\"", text),
+            None => format!("<{}>", fiber.element_type()),
+        };
+
+        let candidate = match candidate {
+            Some(candidate) => candidate,
+            None => {
+                warn(&format!("expected the server HTML to contain a matching {}", expected));
+                self.is_claiming = false;
+
+                return None;
+            }
+        };
+
+        let can_hydrate = match text {
+            Some(text) => host.can_hydrate_text_instance(&candidate, text),
+            None => host.can_hydrate_instance(&candidate, fiber.element_type(), props),
+        };
+
+        if can_hydrate {
+            self.cursor = host.first_hydratable_child(&candidate);
+
+            return Some(candidate);
+        }
+
+        warn(&format!(
+            "expected {} but the server HTML has {}, so it is created again",
+            expected,
+            host.describe_instance(&candidate)
+        ));
+
+        if let Some(parent_instance) = parent_instance {
+            self.deletions.push((parent_instance, candidate));
+        }
+
+        self.is_claiming = false;
+
+        None
+    }
+
+    /// Deletes the instances left unclaimed below a host fiber, or the root, once it completes
+    pub fn complete(&mut self, host: &dyn HostConfig, instance: &Rc<RefCell<Node>>, is_root: bool) {
+        let mut leftover = self.cursor.take().filter(|_| self.is_claiming);
+
+        while let Some(node) = leftover {
+            warn(&format!("the server HTML has an extra {}, so it is removed", host.describe_instance(&node)));
+
+            leftover = host.next_hydratable_sibling(&node);
+            self.deletions.push((Rc::clone(instance), node));
+        }
+
+        if !is_root {
+            if let Some((cursor, is_claiming)) = self.parents.pop() {
+                self.cursor = cursor;
+                self.is_claiming = is_claiming;
+            }
+        }
+    }
+
+    pub fn take_deletions(&mut self) -> Vec<(Rc<RefCell<Node>>, Node)> {
+        std::mem::take(&mut self.deletions)
+    }
+}
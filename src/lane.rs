@@ -0,0 +1,58 @@
+use wasm_bindgen::prelude::*;
+
+/// Priority of an update. Lanes are declared from the most to the least urgent,
+/// so comparing two lanes tells which one has to be rendered first.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Lane {
+    // Updates made inside `flush_sync`, rendered before returning to the caller
+    Sync = 0,
+    // User input such as clicks and key presses
+    Discrete = 1,
+    Default = 2,
+    // Updates that can be interrupted by anything more urgent
+    Transition = 3,
+}
+
+impl Lane {
+    /// Whether an update on this lane has to be applied by a render of `render_lane`
+    pub fn is_included_in(self, render_lane: Lane) -> bool {
+        self <= render_lane
+    }
+}
+
+/// Set of lanes with pending updates
+#[derive(Clone, Copy, Default)]
+pub struct Lanes(u8);
+
+impl Lanes {
+    pub fn insert(&mut self, lane: Lane) {
+        self.0 |= 1 << lane as u8;
+    }
+
+    pub fn remove(&mut self, lane: Lane) {
+        self.0 &= !(1 << lane as u8);
+    }
+
+    pub fn merge(&mut self, other: Lanes) {
+        self.0 |= other.0;
+    }
+
+    /// Whether any of these lanes has to be handled by a render of `render_lane`
+    pub fn has_included_in(&self, render_lane: Lane) -> bool {
+        [Lane::Sync, Lane::Discrete, Lane::Default, Lane::Transition]
+            .iter()
+            .any(|lane| lane.is_included_in(render_lane) && self.contains(*lane))
+    }
+
+    pub fn contains(&self, lane: Lane) -> bool {
+        self.0 & (1 << lane as u8) != 0
+    }
+
+    pub fn highest_priority(&self) -> Option<Lane> {
+        [Lane::Sync, Lane::Discrete, Lane::Default, Lane::Transition]
+            .iter()
+            .find(|lane| self.contains(**lane))
+            .copied()
+    }
+}
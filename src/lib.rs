@@ -1,87 +1,363 @@
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
-use web_sys::{Element as HTMLElement, Text as HTMLText, Window, Document};
+use web_sys::Element as HTMLElement;
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::collections::HashSet;
 use std::rc::Rc;
 use std::mem;
 
+mod children;
+mod class_component;
+mod component;
+mod dom;
 mod element;
+mod error;
 mod fiber;
+mod hook;
+mod host;
+mod hydration;
+mod lane;
+mod root;
+mod scheduler;
+mod server;
+mod suspense;
+mod typescript;
+mod warning;
 mod constants;
-use element::{Element, ElementProps, Node};
-use fiber::{Fiber, FiberCell, FiberEffect, FiberParentIterator};
-use constants::{TEXT_ELEMENT, FIBER_ROOT, FIBER_FUNCTIONAL};
+#[cfg(test)]
+mod test_renderer;
+
+// Lets `html!` refer to the crate by name in its own tests
+#[cfg(test)]
+extern crate self as reactron;
+
+use dom::{DomHost, DomNode};
+pub use children::IntoChildren;
+use class_component::{ClassInstance, merge_state};
+pub use component::{Component, Hooks, SetState};
+pub use element::{Element, ElementBuilder, ElementProps};
+pub use reactron_macro::html;
+use element::{Memo, has_element_children};
+use error::Error;
+use fiber::{ChildSlot, Fiber, FiberCell, FiberEffect, FiberParentIterator, host_instances, host_parent, host_sibling, mark_update_lane, release_fiber_tree};
+use hook::{Hook, HookCell, HookKind, HookState};
+use host::{HostConfig, Node};
+use hydration::Hydration;
+use lane::{Lane, Lanes};
+use root::{Root, RootCell, WorkMark};
+use scheduler::{Deadline, Scheduler, SchedulerKind, Unbounded};
+use server::{HtmlInstance, RenderStream, StringHost};
+use suspense::{SuspenseBoundary, Thenable, is_thenable};
+use warning::warn;
+pub use suspense::Wakeable;
+pub use warning::{WarningSink, set_warning_sink};
+use constants::{TEXT_ELEMENT, FIBER_ROOT, FIBER_FUNCTIONAL, FIBER_CLASS, FIBER_SUSPENSE, YIELD_THRESHOLD_MS, FRAME_BUDGET_MS, STREAM_CHUNK_SIZE};
 
 #[wasm_bindgen]
 pub struct Context {
-    wip_root: Option<FiberCell>,
-    current_root: Option<FiberCell>,
-    next_unit_of_work: Option<FiberCell>,
+    roots: Vec<RootCell>,
+    next_root_id: u32,
+    // Root being rendered or committed
+    wip_root: Option<RootCell>,
     wip_functional_fiber: Option<FiberCell>,
-    effects: Vec<FiberCell>,
-    document: Document
+    // First hook misuse of the component being rendered, which fails its render once it returns
+    hook_error: Option<String>,
+    // What the component being rendered suspended on, if it did
+    suspended_on: Option<Box<dyn Wakeable>>,
+    update_lane: Lane,
+    // Warnings already shown, so they aren't repeated on every render
+    warnings: HashSet<String>,
+
+    // Scheduling
+    scheduler: Rc<dyn Scheduler>,
+    is_work_scheduled: bool,
 }
 
 impl Context {
-    pub fn new() -> Self {
-        let window: Window = web_sys::window().unwrap();
-        let document: Document = window.document().unwrap();
-
+    pub fn new(scheduler: Rc<dyn Scheduler>) -> Self {
         Context {
+            roots: Vec::new(),
+            next_root_id: 0,
             wip_root: None,
-            current_root: None,
-            next_unit_of_work: None,
             wip_functional_fiber: None,
-            effects: Vec::new(),
-            document
+            hook_error: None,
+            suspended_on: None,
+            update_lane: Lane::Default,
+            warnings: HashSet::new(),
+            scheduler,
+            is_work_scheduled: false,
         }
     }
 
     fn add_effect(&mut self, effect: FiberCell) {
-        self.effects.push(effect);
+        if let Some(root) = self.wip_root.as_ref() {
+            root.borrow_mut().add_effect(effect);
+        }
+    }
+
+    fn render_lane(&self) -> Lane {
+        self.wip_root.as_ref().and_then(|root| root.borrow().wip_lane()).unwrap()
     }
 
     pub fn from_ptr(ptr: *mut Context) -> Box<Context> {
         unsafe { Box::from_raw(ptr) }
     }
 
-    fn work_loop(&mut self, did_timeout: bool) -> Result<(), JsValue> {
-        let mut no_next_unit_of_work = self.next_unit_of_work.is_none();
+    fn create_root(&mut self, container: Node, host: Rc<dyn HostConfig>) -> RootCell {
+        let root = Rc::new(RefCell::new(Root::new(self.next_root_id, container, host)));
 
-        loop {
-            if did_timeout || no_next_unit_of_work {
+        self.next_root_id += 1;
+        self.roots.push(Rc::clone(&root));
+
+        root
+    }
+
+    fn create_dom_root(&mut self, container: HTMLElement) -> RootCell {
+        self.create_root(Node::new(DomNode::Element(container)), Rc::new(DomHost::new()))
+    }
+
+    fn get_root(&self, root_id: u32) -> Option<RootCell> {
+        self.roots.iter()
+            .find(|root| root.borrow().id() == root_id)
+            .map(Rc::clone)
+    }
+
+    fn find_root(&self, container: &HTMLElement) -> Option<RootCell> {
+        self.roots.iter()
+            .find(|root| dom::is_container(&root.borrow().container().borrow(), container))
+            .map(Rc::clone)
+    }
+
+    fn ensure_work_scheduled(&mut self, context_ptr: *mut Context) {
+        if self.is_work_scheduled {
+            return;
+        }
+
+        self.is_work_scheduled = true;
+        self.scheduler.schedule(Box::new(move |deadline| perform_scheduled_work(context_ptr, deadline)));
+    }
+
+    fn has_pending_work(&self) -> bool {
+        self.roots.iter().any(|root| root.borrow().next_lane().is_some())
+    }
+
+    /// Root with the most urgent work, so an update on one root isn't held back by
+    /// a less urgent render of another one
+    fn most_urgent_root(&self) -> Option<RootCell> {
+        self.roots.iter()
+            .filter_map(|root| root.borrow().next_lane().map(|lane| (lane, root)))
+            .min_by_key(|(lane, _)| *lane)
+            .map(|(_, root)| Rc::clone(root))
+    }
+
+    fn work_loop(&mut self, deadline: &dyn Deadline) -> Result<bool, Error> {
+        while let Some(root) = self.most_urgent_root() {
+            if deadline.should_yield() {
                 break;
             }
 
-            let wip_fiber = Rc::clone(&self.next_unit_of_work.as_ref().unwrap());
-            self.next_unit_of_work = self.perform_unit_of_work(wip_fiber);
-            
-            no_next_unit_of_work = self.next_unit_of_work.is_none();
+            self.wip_root = Some(Rc::clone(&root));
+            let result = self.work_on_root(&root, deadline);
+            self.wip_root = None;
+
+            result?;
+        }
+
+        Ok(self.has_pending_work())
+    }
+
+    fn work_on_root(&mut self, root: &RootCell, deadline: &dyn Deadline) -> Result<(), Error> {
+        // Every update batched since the last render is handled by a single one
+        root.borrow_mut().prepare_fresh_stack();
+
+        loop {
+            let wip_fiber = root.borrow().next_unit_of_work().map(Rc::clone);
+
+            let wip_fiber = match wip_fiber {
+                Some(wip_fiber) => wip_fiber,
+                None => break,
+            };
+
+            if deadline.should_yield() {
+                return Ok(());
+            }
+
+            let next_unit_of_work = match self.perform_unit_of_work(wip_fiber) {
+                Ok(next_unit_of_work) => next_unit_of_work,
+                Err(error) => {
+                    // Nothing of a render that failed is committed, the tree stays as it was
+                    root.borrow_mut().discard_work();
+                    return Err(error);
+                },
+            };
+
+            // A more urgent update made while rendering may have abandoned this render
+            if root.borrow().wip().is_none() {
+                return Ok(());
+            }
+
+            root.borrow_mut().set_next_unit_of_work(next_unit_of_work);
+        }
+
+        if root.borrow().wip().is_some() {
+            self.commit_root(root)?;
         }
 
-        if no_next_unit_of_work && self.wip_root.is_some() {
-            self.commit_root()?;
+        Ok(())
+    }
+
+    /// Renders `element` synchronously into a detached root, and returns its markup
+    fn render_to_string(&mut self, element: Element) -> Result<String, Error> {
+        let container = HtmlInstance::container();
+        let root = self.create_root(Node::new(container.clone()), Rc::new(StringHost));
+
+        {
+            let mut root = root.borrow_mut();
+            root.set_children(vec![element]);
+            root.schedule_update(Lane::Sync);
+        }
+
+        // Other roots keep waiting for the scheduler
+        self.wip_root = Some(Rc::clone(&root));
+        let result = self.work_on_root(&root, &Unbounded);
+        self.wip_root = None;
+
+        // Updates made while rendering have nowhere to be committed
+        self.remove_root(&root);
+
+        result.map(|_| container.inner_html())
+    }
+
+    fn perform_sync_work(&mut self) -> Result<(), Error> {
+        // Other lanes are left to the scheduler
+        while let Some(root) = self.most_urgent_root() {
+            if root.borrow().next_lane() != Some(Lane::Sync) {
+                break;
+            }
+
+            self.wip_root = Some(Rc::clone(&root));
+            let result = self.work_on_root(&root, &Unbounded);
+            self.wip_root = None;
+
+            result?;
         }
 
         Ok(())
     }
 
-    fn perform_unit_of_work(&mut self, wip_fiber: FiberCell) -> Option<FiberCell> {
+    fn perform_unit_of_work(&mut self, wip_fiber: FiberCell) -> Result<Option<FiberCell>, Error> {
+        let render_lane = self.render_lane();
+
+        if wip_fiber.borrow().is_suspense_boundary() {
+            self.enter_suspense_boundary(&wip_fiber);
+        }
+
+        let can_bail_out = wip_fiber.borrow().can_bail_out(render_lane);
         let is_functional_tree = wip_fiber.borrow().is_functional_tree();
 
-        if is_functional_tree {
+        let mut skip_children = false;
+
+        if can_bail_out {
+            let has_child_work = wip_fiber.borrow().child_lanes().has_included_in(render_lane);
+
+            if has_child_work {
+                // Nothing changed for this fiber, so its children are copied as they were
+                // until reaching the fibers that were updated
+                self.clone_child_fibers(&wip_fiber);
+            } else {
+                // Nor anywhere below it, so the whole subtree is reused without walking it
+                self.reuse_child_fibers(&wip_fiber);
+                skip_children = true;
+            }
+        } else if is_functional_tree {
             let fiber = wip_fiber.borrow();
 
-            let func = Rc::clone(&fiber.component_function().unwrap());
-            let props = Rc::clone(&fiber.component_function_props().unwrap());
+            let component = fiber.component().map(Rc::clone);
+            let func = fiber.component_function().map(Rc::clone);
+            let props = fiber.component_function_props().map(Rc::clone);
+            let is_class_component = fiber.is_class_component();
+
+            // Function components beneath StrictMode are rendered twice in debug builds, so
+            // side effects in render show up. Only the second render is kept. Components given
+            // children are rendered once, as a render consumes the element handles in their
+            // props, and class components, which get theirs the same way, are remounted instead.
+            let is_rendered_twice = cfg!(debug_assertions) && fiber.is_strict() && !is_class_component &&
+                !props.as_ref().is_some_and(|props| has_element_children(props));
+            let renders = if is_rendered_twice { 2 } else { 1 };
 
             // Drop the borrow so it can be borrowed from 'use_state'
             mem::drop(fiber);
 
+            let wip_root = Rc::clone(self.wip_root.as_ref().unwrap());
+            let mark = wip_root.borrow().mark_work();
+            let mut child = None;
+
             self.wip_functional_fiber = Some(Rc::clone(&wip_fiber));
-            let child = self.execute_function_component(func, props);
+
+            for _ in 0..renders {
+                // Hooks copied from a reused fiber, or added by a discarded render, are added again
+                wip_fiber.borrow_mut().reset_hooks();
+                wip_root.borrow_mut().rewind_work(mark);
+
+                let rendered = match (component.clone(), func.clone()) {
+                    (Some(component), _) => Ok(self.execute_rust_component(component)),
+                    (None, Some(class)) if is_class_component => {
+                        self.execute_class_component(&wip_fiber, class, props.clone().unwrap())
+                    },
+                    (None, Some(func)) => self.execute_function_component(func, props.clone().unwrap()),
+                    // Fragments keep the children of their element
+                    (None, None) => Ok(None),
+                };
+
+                // A JS component waiting for something throws a promise of it, and a Rust one
+                // suspends through its hooks
+                let suspended_on = match (&rendered, self.suspended_on.take()) {
+                    (Err(thrown), _) if is_thenable(thrown) => Some(Box::new(Thenable(thrown.clone())) as Box<dyn Wakeable>),
+                    (_, suspended_on) => suspended_on,
+                };
+
+                // Hooks called out of order are reported once the component returned
+                if let Some(message) = self.hook_error.take() {
+                    self.wip_functional_fiber = None;
+                    return Err(Error::Invalid(message));
+                }
+
+                if let Some(wakeable) = suspended_on {
+                    self.wip_functional_fiber = None;
+                    return self.suspend(&wip_fiber, wakeable).map(Some);
+                }
+
+                child = match rendered {
+                    Ok(child) => child,
+                    Err(error) => {
+                        self.wip_functional_fiber = None;
+                        return Err(Error::Js(error));
+                    },
+                };
+            }
+
             self.wip_functional_fiber = None;
 
+            let fiber = wip_fiber.borrow();
+
+            // Hooks past the last one were taken from the previous render by `mount_state` already
+            if let Some(alternate) = fiber.alternate() {
+                let previous_count = alternate.borrow().hook_count();
+
+                if fiber.hook_count() < previous_count {
+                    return Err(Error::Invalid(format!(
+                        "{} rendered {} hooks, fewer than the {} of its previous render. Hooks have to be called in the same order every render, never conditionally or after an early return.",
+                        fiber.component_name(),
+                        fiber.hook_count(),
+                        previous_count
+                    )));
+                }
+            }
+
+            mem::drop(fiber);
+
             let mut fiber = wip_fiber.borrow_mut();
 
             if let Some(child) = child {
@@ -89,12 +365,24 @@ impl Context {
                 fiber.set_element_children(Some(Rc::new(RefCell::new(children_vec))));
             }
 
+            // Only the updates skipped by this render are left pending on the fiber
+            let mut remaining_lanes = Lanes::default();
+
+            for hook in fiber.hooks().into_iter().flatten() {
+                remaining_lanes.merge(hook.borrow().skipped_lanes(render_lane));
+            }
+
+            fiber.set_lanes(remaining_lanes);
+
             self.reconcile_children(&wip_fiber, &mut fiber);
         } else {
             let mut fiber = wip_fiber.borrow_mut();
 
             if fiber.dom_node().is_none() {
-                let dom_node = self.create_dom_node(&fiber);
+                let dom_node = match self.claim_instance(&mut fiber) {
+                    Some(dom_node) => dom_node,
+                    None => self.create_instance(&fiber),
+                };
 
                 fiber.set_dom_node(Rc::new(RefCell::new(dom_node)));
             }
@@ -110,245 +398,409 @@ impl Context {
         }
 
         // If fiber has a child, make it the next unit of work
-        if let Some(fiber_child) = &fiber.child() {
-            // console_log!("{} has a child", &fiber.element_type());
-            return Some(Rc::clone(fiber_child));
+        if let Some(fiber_child) = fiber.child().as_ref().filter(|_| !skip_children) {
+            return Ok(Some(Rc::clone(fiber_child)));
+        }
 
-        // ...or if it has a sibling, make it the next unit of work
-        } else if let Some(fiber_sibling) = &fiber.sibling() {
-            // console_log!("{} has a sibling", &fiber.element_type());
-            return Some(Rc::clone(fiber_sibling));
+        // Drop the mutable borrow to avoid crashing when looping through the parents
+        mem::drop(fiber);
 
-        // Otherwise look for the closest parent's sibling
-        } else {
-            // console_log!("{} has no child or sibling. coming back to parents", &fiber.element_type());
+        // Otherwise complete the fiber and its parents until one of them has a sibling
+        let mut completed_fiber = Some(wip_fiber);
 
-            // Drop the mutable borrow to avoid crashing when looping through the parents
-            mem::drop(fiber);
+        while let Some(fiber) = completed_fiber {
+            self.complete_unit_of_work(&fiber);
 
-            for parent in wip_fiber.parents() {
-                if let Some(parent_sibling) = &parent.borrow().sibling() {
-                    return Some(Rc::clone(parent_sibling));
-                }
+            let fiber = fiber.borrow();
+
+            if let Some(fiber_sibling) = fiber.sibling() {
+                return Ok(Some(Rc::clone(fiber_sibling)));
+            }
+
+            completed_fiber = fiber.parent().as_ref().map(Rc::clone);
+        }
+
+        Ok(None)
+    }
+
+    fn complete_unit_of_work(&self, wip_fiber: &FiberCell) {
+        let mut fiber = wip_fiber.borrow_mut();
+        let mut child_lanes = Lanes::default();
+        let mut child_fiber = fiber.child().as_ref().map(Rc::clone);
+
+        // Collect the work left behind in the subtree
+        while let Some(child) = child_fiber {
+            let child = child.borrow();
+            child_lanes.merge(child.lanes());
+            child_lanes.merge(child.child_lanes());
+
+            child_fiber = child.sibling().as_ref().map(Rc::clone);
+        }
+
+        fiber.set_child_lanes(child_lanes);
+
+        if let Some(dom_node) = fiber.dom_node() {
+            let is_root = fiber.parent().is_none();
+            let host = self.host();
+
+            // The root's instance is the container, which isn't rendered by it
+            if !is_root {
+                host.complete_instance(&dom_node.borrow());
+            }
+
+            let root = self.wip_root.as_ref().unwrap();
+
+            if let Some(hydration) = root.borrow_mut().hydration_mut() {
+                hydration.complete(&*host, dom_node, is_root);
             }
         }
+    }
+
+    fn reuse_child_fibers(&self, wip_fiber: &FiberCell) {
+        let mut fiber = wip_fiber.borrow_mut();
+        let first_child = fiber.alternate().and_then(|alternate| {
+            alternate.borrow().child().as_ref().map(Rc::clone)
+        });
+
+        let mut child_fiber = first_child.as_ref().map(Rc::clone);
+
+        // The children now belong to both trees, but updates must find their way up this one
+        while let Some(child) = child_fiber {
+            let mut child = child.borrow_mut();
+            child.set_parent(Rc::clone(wip_fiber));
+
+            child_fiber = child.sibling().as_ref().map(Rc::clone);
+        }
+
+        if let Some(first_child) = first_child {
+            fiber.set_child(first_child);
+        }
+    }
+
+    fn clone_child_fibers(&self, wip_fiber: &FiberCell) {
+        let mut fiber = wip_fiber.borrow_mut();
+        let mut previous_sibling: Option<FiberCell> = None;
+        let mut old_child_fiber = fiber.alternate().and_then(|alternate| {
+            alternate.borrow().child().as_ref().map(Rc::clone)
+        });
+
+        while let Some(old_child) = old_child_fiber {
+            let mut child_fiber = Fiber::reuse(&old_child.borrow());
+            child_fiber.set_alternate(Rc::clone(&old_child));
+            child_fiber.set_parent(Rc::clone(wip_fiber));
+
+            let child_fiber = Rc::new(RefCell::new(Box::new(child_fiber)));
 
-        return None;
+            // Setters have to find the fiber that is about to become current
+            for hook in child_fiber.borrow().hooks().into_iter().flatten() {
+                hook.borrow_mut().set_fiber(&child_fiber);
+            }
+
+            if let Some(previous_sibling) = previous_sibling {
+                previous_sibling.borrow_mut().set_sibling(Rc::clone(&child_fiber));
+            } else {
+                fiber.set_child(Rc::clone(&child_fiber));
+            }
+
+            previous_sibling = Some(child_fiber);
+            old_child_fiber = old_child.borrow().sibling().as_ref().map(Rc::clone);
+        }
     }
 
     fn execute_function_component(
         &self,
         func: Rc<js_sys::Function>,
         props: Rc<JsValue>
-    ) -> Option<Box<Element>> {
-        func.call1(&JsValue::null(), &props)
-            .unwrap()
+    ) -> Result<Option<Box<Element>>, JsValue> {
+        Ok(func.call1(&JsValue::null(), &props)?
             .as_f64()
-            .map(|child_ptr| Element::from_ptr(child_ptr as u32 as *mut Element))
+            .map(|child_ptr| Element::from_ptr(child_ptr as u32 as *mut Element)))
     }
 
-    fn create_dom_node(&self, fiber: &Fiber) -> Node {
-        let props = fiber.props().unwrap();
+    fn execute_rust_component(&mut self, component: Rc<dyn Component>) -> Option<Box<Element>> {
+        // Hooks reach the context the same way JS components do
+        let context_ptr: *mut Context = self;
 
-        if fiber.is_text_fiber() {
-            let node: HTMLText = self.document.create_text_node(&props.node_value().unwrap());
+        component.render(&mut Hooks::new(context_ptr))
+    }
 
-            Node::Text(node)
-        } else {
-            let node = self.document.create_element(fiber.element_type()).unwrap();
-            self.update_dom_node(&node, None, &props);
-
-            Node::Element(node)
-        }
-    }
-
-    fn update_dom_node(&self, dom_node: &HTMLElement, prev_props: Option<&Box<ElementProps>>, next_props: &Box<ElementProps>) {
-        let prev_class_name = prev_props.and_then(|p| p.class_name());
-        let next_class_name = next_props.class_name();
-
-        let prev_on_click = prev_props.and_then(|p| p.on_click());
-        let next_on_click = next_props.on_click();
-        let prev_on_change = prev_props.and_then(|p| p.on_change());
-        let next_on_change = next_props.on_change();
-        let prev_on_blur = prev_props.and_then(|p| p.on_blur());
-        let next_on_blur = next_props.on_blur();
-        let prev_on_keydown = prev_props.and_then(|p| p.on_keydown());
-        let next_on_keydown = next_props.on_keydown();
-        let prev_input_type = prev_props.and_then(|p| p.input_type());
-        let next_input_type = next_props.input_type();
-        let prev_input_value = prev_props.and_then(|p| p.input_value());
-        let next_input_value = next_props.input_value();
-        let prev_input_placeholder = prev_props.and_then(|p| p.input_placeholder());
-        let next_input_placeholder = next_props.input_placeholder();
-        let prev_input_checked = prev_props.and_then(|p| p.input_checked());
-        let next_input_checked = next_props.input_checked();
-
-        // Class name
-        match (prev_class_name, next_class_name) {
-            (Some(prev), Some(next)) => {
-                if *prev != *next {
-                    dom_node.set_class_name(next);
-                }
-            },
-            (None, Some(next)) => {
-                dom_node.set_class_name(next);
+    fn execute_class_component(
+        &mut self,
+        wip_fiber: &FiberCell,
+        class: Rc<js_sys::Function>,
+        props: Rc<JsValue>
+    ) -> Result<Option<Box<Element>>, JsValue> {
+        let context_ptr: *mut Context = self;
+
+        // The instance is created on mount and then carried over by every alternate
+        let instance = wip_fiber.borrow().class_instance().map(Rc::clone);
+        let instance = match instance {
+            Some(instance) => instance,
+            None => {
+                // A throwing constructor is reported like a throwing `render`
+                let is_strict = wip_fiber.borrow().is_strict();
+                let instance = Rc::new(ClassInstance::new(&class, &props, is_strict)?);
+                wip_fiber.borrow_mut().set_class_instance(Some(Rc::clone(&instance)));
+
+                instance
             },
-            (_, _) => {}
-        }
+        };
+
+        // The state is kept by a hook, so `setState` updates get lanes as `useState` ones do
+        let (state, hook) = mount_state(context_ptr, HookKind::State, HookState::Js(instance.initial_state()));
+        let state = match state {
+            HookState::Js(state) => state,
+            HookState::Rust(_) => JsValue::undefined(),
+        };
+
+        if !instance.has_updater() {
+            let weak_instance = Rc::downgrade(&instance);
+
+            instance.set_updater(Closure::wrap(Box::new(move |partial_state: JsValue| {
+                let latest_state = match hook.borrow().latest_state() {
+                    HookState::Js(state) => state,
+                    HookState::Rust(_) => JsValue::undefined(),
+                };
 
-        // Input type
-        match (prev_input_type, next_input_type) {
-            (None, Some(next)) => {
-                dom_node.unchecked_ref::<web_sys::HtmlInputElement>()
-                    .set_type(next);
-            },
-            (_, _) => {}
+                // `setState(updater)` computes the partial state from the latest one
+                let partial_state = match partial_state.dyn_ref::<js_sys::Function>() {
+                    Some(updater) => {
+                        let props = weak_instance.upgrade().map_or(JsValue::undefined(), |instance| instance.props());
+                        // ...and what it throws is thrown by `setState`
+                        updater.call2(&JsValue::null(), &latest_state, &props)?
+                    },
+                    None => partial_state,
+                };
+
+                dispatch_state(context_ptr, &hook, HookState::Js(merge_state(&latest_state, &partial_state)));
+
+                Ok(())
+            }) as Box<dyn FnMut(JsValue) -> Result<(), JsValue>>));
         }
 
-        // Input value
-        match (prev_input_value, next_input_value) {
-            (_, Some(next)) => {
-                dom_node.unchecked_ref::<web_sys::HtmlInputElement>()
-                    .set_value(next);
-            },
-            (_, _) => {}
+        self.wip_root.as_ref().unwrap().borrow_mut().add_wip_class_instance(Rc::clone(&instance));
+
+        instance.render(&props, &state)
+    }
+
+    fn enter_suspense_boundary(&mut self, wip_fiber: &FiberCell) {
+        let wip_root = Rc::clone(self.wip_root.as_ref().unwrap());
+        let mut fiber = wip_fiber.borrow_mut();
+
+        let boundary = fiber.suspense().map(Rc::clone).unwrap_or_else(|| {
+            let boundary = Rc::new(SuspenseBoundary::default());
+            fiber.set_suspense(Some(Rc::clone(&boundary)));
+
+            boundary
+        });
+
+        // Everything done from here on belongs to the boundary's subtree, until it completes
+        boundary.set_mark(wip_root.borrow().mark_work());
+        boundary.set_rendered_by(wip_fiber, &wip_root);
+    }
+
+    /// Unwinds the render to the nearest Suspense boundary that isn't suspended already, which
+    /// renders its fallback until `wakeable` settles. Returns the boundary, to be rendered again.
+    fn suspend(&mut self, wip_fiber: &FiberCell, wakeable: Box<dyn Wakeable>) -> Result<FiberCell, Error> {
+        let boundary_fiber = wip_fiber.parents()
+            .find(|parent| {
+                let parent = parent.borrow();
+                parent.is_suspense_boundary() && !parent.is_suspended()
+            })
+            .ok_or_else(|| Error::Invalid(format!(
+                "{} suspended while rendering, but there's no Suspense boundary above it to render a fallback",
+                wip_fiber.borrow().component_name()
+            )))?;
+
+        let boundary = Rc::clone(boundary_fiber.borrow().suspense().unwrap());
+
+        if let Some(mark) = boundary.take_mark() {
+            self.wip_root.as_ref().unwrap().borrow_mut().rewind_work(mark);
         }
 
-        // Input checked
-        match (prev_input_checked, next_input_checked) {
-            (_, Some(next)) => {
-                dom_node.unchecked_ref::<web_sys::HtmlInputElement>()
-                    .set_checked(next);
-            },
-            (_, _) => {}
+        // Only this render shows the fallback, the committed boundary keeps its children until it commits
+        boundary_fiber.borrow_mut().set_suspended(true);
+
+        // Rendered again with the fallback, instead of bailing out
+        let render_lane = self.render_lane();
+        boundary_fiber.borrow_mut().mark_lane(render_lane);
+
+        if !self.host().is_static() {
+            let context_ptr: *mut Context = self;
+            wakeable.when_settled(Box::new(move || retry_suspense_boundary(context_ptr, &boundary)));
         }
 
-        // Input placeholder
-        match (prev_input_placeholder, next_input_placeholder) {
-            (Some(prev), Some(next)) => {
-                if *prev != *next {
-                    dom_node.unchecked_ref::<web_sys::HtmlInputElement>()
-                        .set_placeholder(next);
-                }
-            },
-            (None, Some(next)) => {
-                dom_node.unchecked_ref::<web_sys::HtmlInputElement>()
-                    .set_placeholder(next);
-            }
-            (_, _) => {}
+        Ok(boundary_fiber)
+    }
+
+    /// Fails the render of `fiber` once its component returns, with the first misuse of its hooks
+    fn report_hook_error(&mut self, fiber: &Fiber, message: &str) {
+        if self.hook_error.is_none() {
+            self.hook_error = Some(format!("{} {}", fiber.component_name(), message));
         }
+    }
 
-        self.update_listener(
-            "click",
-            &prev_on_click,
-            &next_on_click,
-            &dom_node
-        );
+    fn host(&self) -> Rc<dyn HostConfig> {
+        Rc::clone(self.wip_root.as_ref().unwrap().borrow().host())
+    }
 
-        self.update_listener(
-            "change",
-            &prev_on_change,
-            &next_on_change,
-            &dom_node
-        );
+    /// Instance the fiber can take over when hydrating, instead of creating one
+    fn claim_instance(&self, fiber: &mut Fiber) -> Option<Node> {
+        let host = self.host();
+        let mut root = self.wip_root.as_ref().unwrap().borrow_mut();
+        let hydration = root.hydration_mut()?;
 
-        self.update_listener(
-            "blur",
-            &prev_on_blur,
-            &next_on_blur,
-            &dom_node
-        );
+        let parent_instance = fiber.parent().as_ref().and_then(|parent| {
+            parent.borrow().dom_node().map(Rc::clone).or_else(|| host_parent(parent))
+        });
 
-        self.update_listener(
-            "keydown",
-            &prev_on_keydown,
-            &next_on_keydown,
-            &dom_node
-        );
+        let instance = hydration.claim(&*host, fiber, parent_instance)?;
+        fiber.set_effect_tag(FiberEffect::Hydration);
+
+        Some(instance)
     }
 
-    fn update_listener(
-        &self,
-        event_type: &str,
-        prev_listener: &Option<&js_sys::Function>,
-        next_listener: &Option<&js_sys::Function>,
-        dom_node: &HTMLElement,
-    ) {
-        match (prev_listener, next_listener) {
-            (None, Some(callback)) => {
-                dom_node.add_event_listener_with_callback(event_type, callback).unwrap();
-            },
-            (Some(callback), None) => {
-                dom_node.remove_event_listener_with_callback(event_type, callback).unwrap();
-            },
-            (Some(prev_callback), Some(next_callback)) => {
-                dom_node.remove_event_listener_with_callback(event_type, prev_callback).unwrap();
-                dom_node.add_event_listener_with_callback(event_type, next_callback).unwrap();
-            },
-            (_, _) => {}
+    fn create_instance(&self, fiber: &Fiber) -> Node {
+        let props = fiber.props().unwrap();
+        let host = self.host();
+
+        if fiber.is_text_fiber() {
+            host.create_text_instance(props.node_value().unwrap())
+        } else {
+            host.create_instance(fiber.element_type(), props)
         }
     }
 
-    fn update_dom_text(&self, text_node: &HTMLText, prev_props: Option<&Box<ElementProps>>, next_props: &Box<ElementProps>) {
-        let prev_value = prev_props.and_then(|p| p.node_value());
-        let next_value = next_props.node_value();
+    fn warn_once(&mut self, message: String) {
+        if !self.warnings.contains(&message) {
+            warn(&message);
+            self.warnings.insert(message);
+        }
+    }
 
-        match (prev_value, next_value) {
-            (Some(prev), Some(next)) => {
-                if *prev != *next {
-                    text_node.set_node_value(Some(next));
-                }
-            },
-            (None, Some(next)) => {
-                text_node.set_node_value(Some(next));
-            },
-            (_, _) => {}
+    /// Warns about children that can't be told apart from their siblings, and so may take
+    /// over the fiber and state of another one when the list changes
+    fn warn_about_keys(&mut self, fiber: &Fiber) {
+        let children = match fiber.element_children() {
+            Some(children) => children.borrow(),
+            None => return,
+        };
+
+        let mut keys = HashSet::new();
+        let mut is_key_missing = false;
+        let mut duplicate_key = None;
+
+        for child in children.iter() {
+            match child.key() {
+                Some(key) if !keys.insert(key) => duplicate_key = duplicate_key.or(Some(key)),
+                Some(_) => {},
+                None => is_key_missing |= child.is_list_item(),
+            }
+        }
+
+        if !is_key_missing && duplicate_key.is_none() {
+            return;
+        }
+
+        let parent = if fiber.is_functional_tree() {
+            String::from("a fragment")
+        } else if fiber.element_type() == FIBER_ROOT {
+            String::from("the root")
+        } else {
+            format!("<{}>", fiber.element_type())
+        };
+
+        let location = match owner_component_name(fiber) {
+            Some(owner) => format!("{} rendered by {}", parent, owner),
+            None => parent,
+        };
+
+        let duplicate_key = duplicate_key.cloned();
+        mem::drop(children);
+
+        if is_key_missing {
+            self.warn_once(format!(
+                "each child in a list should have a unique key, so it keeps its state when the list changes. Check the children of {}.",
+                location
+            ));
+        }
+
+        if let Some(key) = duplicate_key {
+            self.warn_once(format!(
+                "children of {} share the key \"{}\". Keys have to be unique among siblings, or their state gets mixed up.",
+                location,
+                key
+            ));
         }
     }
 
     fn reconcile_children(&mut self, wip_unit: &FiberCell, fiber: &mut Fiber) {
-        let children = fiber.element_children().as_ref();
+        if cfg!(debug_assertions) {
+            self.warn_about_keys(fiber);
+        }
+
+        let children = fiber.rendered_children();
         let children_len = children.map_or(0, |children| children.borrow().len());
 
-        let mut i = 0;
         let mut previous_sibling: Option<FiberCell> = None;
         let mut first_child_fiber: Option<FiberCell> = None;
 
-        let mut old_child_fiber = fiber.alternate().map_or(None, |alternate| {
-            alternate.borrow()
-                .child()
-                .as_ref()
-                .map_or(None, |child| {
-                    Some(Rc::clone(child))
-                })
-        });
+        // Old children are matched by their key, wherever they were, or else by their index
+        let mut old_children: HashMap<ChildSlot, (usize, FiberCell)> = HashMap::new();
+        let mut deletions = Vec::new();
 
-        while i < children_len || old_child_fiber.as_ref().is_some() {
-            let mut children = children.unwrap().borrow_mut();
-            let child_element = children.get_mut(i);
+        let mut old_child_fiber = fiber.alternate().and_then(|alternate| alternate.borrow().child().as_ref().map(Rc::clone));
+        let mut old_index = 0;
 
-            let has_same_type = old_child_fiber.as_ref().map_or(false, |old_child| {
-                child_element.as_ref().map_or(false, |child| {
-                    *old_child.borrow().element_type() == *child.element_type()
-                })
-            });
+        while let Some(old_child) = old_child_fiber {
+            old_child_fiber = old_child.borrow().sibling().as_ref().map(Rc::clone);
+
+            let slot = ChildSlot::new(old_child.borrow().key(), old_index);
+
+            // Only the first of the siblings sharing a key can be matched
+            match old_children.entry(slot) {
+                Entry::Occupied(_) => deletions.push((old_index, old_child)),
+                Entry::Vacant(entry) => {
+                    entry.insert((old_index, old_child));
+                },
+            }
+
+            old_index += 1;
+        }
+
+        // Index of the rightmost old child kept in place, anything matched before it has moved
+        let mut last_placed_index = 0;
+
+        for i in 0..children_len {
+            let children = children.unwrap().borrow();
+            let child_element = &children[i];
+            let old_child = old_children.remove(&ChildSlot::new(child_element.key(), i));
 
-            let child_fiber = child_element.map_or(None, |child_element| {
-                // Generate a new Fiber for the updated node
-                let mut child_fiber = if has_same_type {
-                    let alternate_child = old_child_fiber.as_ref().unwrap();
-                    let mut child_fiber = Fiber::new(&alternate_child.borrow().element_type());
+            let has_same_type = old_child.as_ref().is_some_and(|(_, old_child)| {
+                let old_child = old_child.borrow();
 
-                    child_fiber.set_props(child_element.props_mut().take());
+                // A different key means a different element, even of the same type
+                let is_same_element = *old_child.element_type() == *child_element.element_type() && old_child.key() == child_element.key();
 
-                    let element_children = child_element.children_mut().take().map(|children| {
-                        Rc::new(RefCell::new(children))
-                    });
+                // ...and hooks or class instances are only carried over to the same component
+                is_same_element && (!old_child.is_functional_tree() || old_child.is_same_component(child_element))
+            });
+
+            // Generate a new Fiber for the updated node
+            let mut child_fiber = match old_child {
+                Some((old_index, alternate_child)) if has_same_type => {
+                    let mut child_fiber = Fiber::new(alternate_child.borrow().element_type());
 
-                    child_fiber.set_element_children(element_children);
+                    child_fiber.set_props(child_element.props().clone());
+
+                    child_fiber.set_element_children(child_element.children().clone());
 
                     // relate to alternate
                     child_fiber.set_alternate(Rc::clone(&alternate_child));
+                    child_fiber.set_class_instance(alternate_child.borrow().class_instance().cloned());
+                    child_fiber.set_suspense(alternate_child.borrow().suspense().cloned());
+                    child_fiber.set_lanes(alternate_child.borrow().lanes());
+                    child_fiber.set_child_lanes(alternate_child.borrow().child_lanes());
 
                     // set existing dom node
                     if let Some(old_child_node) = alternate_child.borrow().dom_node() {
@@ -359,24 +811,33 @@ impl Context {
                     child_fiber.set_parent(Rc::clone(wip_unit));
 
                     // effect
-                    if !child_fiber.is_functional_tree() {
-                        if let Some(old_props) = alternate_child.borrow().props() {
-                            if child_fiber.has_props_changed(old_props) {
-                                child_fiber.set_effect_tag(FiberEffect::Update);
-                                // console_log!("added UPDATE effect for {}", &child_fiber.element_type());
+                    if old_index < last_placed_index {
+                        // Moved instances are placed again, and updated along if their props changed
+                        child_fiber.set_effect_tag(FiberEffect::Placement);
+                    } else {
+                        last_placed_index = old_index;
+
+                        if !child_fiber.is_functional_tree() {
+                            if let Some(old_props) = alternate_child.borrow().props() {
+                                if child_fiber.has_props_changed(old_props) {
+                                    child_fiber.set_effect_tag(FiberEffect::Update);
+                                    // console_log!("added UPDATE effect for {}", &child_fiber.element_type());
+                                }
                             }
                         }
                     }
 
-                    Some(child_fiber)
-                } else {
-                    let mut child_fiber = Fiber::new(&child_element.element_type());
+                    child_fiber
+                },
+                old_child => {
+                    if let Some(old_child) = old_child {
+                        deletions.push(old_child);
+                    }
+
+                    let mut child_fiber = Fiber::new(child_element.element_type());
 
-                    child_fiber.set_props(child_element.props_mut().take());
-                    let element_children = child_element.children_mut().take().map(|children| {
-                        Rc::new(RefCell::new(children))
-                    });
-                    child_fiber.set_element_children(element_children);
+                    child_fiber.set_props(child_element.props().clone());
+                    child_fiber.set_element_children(child_element.children().clone());
 
                     // relate to parent (current fiber)
                     child_fiber.set_parent(Rc::clone(wip_unit));
@@ -387,60 +848,43 @@ impl Context {
                         // console_log!("added PLACEMENT effect for {}", &child_fiber.element_type());
                     }
 
-                    Some(child_fiber)
-                };
-
-                child_fiber.as_mut().map(|child_fiber| {
-                    if child_fiber.is_functional_tree() {
-                        let func = child_element.component_function().unwrap();
-                        let props = child_element.component_function_props().unwrap();
-
-                        child_fiber.set_component_function(Some(Rc::clone(&func)));
-                        child_fiber.set_component_function_props(Some(Rc::clone(&props)));
-                        child_fiber.set_hooks(Some(vec![]));
-                    }
-                });
-
-                child_fiber
-            });
-
-            if let Some(old_child_fiber) = old_child_fiber.as_ref() {
-                if !has_same_type {
-                    old_child_fiber.borrow_mut().set_effect_tag(FiberEffect::Deletion);
-                    self.add_effect(Rc::clone(&old_child_fiber));
-                    // console_log!("added deletion effect for {}", old_child_fiber.borrow().element_type());
-                }
+                    child_fiber
+                },
+            };
+
+            child_fiber.set_key(child_element.key().cloned());
+            child_fiber.set_strict(fiber.is_strict() || child_element.is_strict_mode());
+
+            if child_fiber.is_functional_tree() {
+                child_fiber.set_component_function(child_element.component_function().cloned());
+                child_fiber.set_component_function_props(child_element.component_function_props().cloned());
+                child_fiber.set_component(child_element.component().cloned());
+                child_fiber.set_memo(child_element.memo().cloned());
+                child_fiber.set_fallback(child_element.fallback().cloned());
+                child_fiber.set_hooks(Some(vec![]));
             }
 
-            if old_child_fiber.is_some() {
-                let old_child_sibling = {
-                    let old_child = old_child_fiber.as_ref().unwrap().borrow();
-                    if let Some(sibling) = old_child.sibling() {
-                        Some(Rc::clone(sibling))
-                    } else {
-                        None
-                    }
-                };
+            let child_fiber = Rc::new(RefCell::new(Box::new(child_fiber)));
 
-                old_child_fiber = old_child_sibling;
+            if i == 0 {
+                first_child_fiber = Some(Rc::clone(&child_fiber));
+            } else {
+                if let Some(previous_sibling) = previous_sibling {
+                    previous_sibling.borrow_mut().set_sibling(Rc::clone(&child_fiber));
+                }
             }
 
-            
-            if let Some(child_fiber) = child_fiber {
-                let child_fiber = Rc::new(RefCell::new(Box::new(child_fiber)));
+            previous_sibling = Some(Rc::clone(&child_fiber));
+        }
 
-                if i == 0 {
-                    first_child_fiber = Some(Rc::clone(&child_fiber));
-                } else {
-                    if let Some(previous_sibling) = previous_sibling {
-                        previous_sibling.borrow_mut().set_sibling(Rc::clone(&child_fiber));
-                    }
-                }
-                
-                previous_sibling = Some(Rc::clone(&child_fiber));
-                i += 1;
-            }
+        // Old children nothing was matched with are deleted, in the order they were in
+        deletions.extend(old_children.into_values());
+        deletions.sort_by_key(|(old_index, _)| *old_index);
 
+        for (_, old_child_fiber) in deletions {
+            old_child_fiber.borrow_mut().set_effect_tag(FiberEffect::Deletion);
+            self.add_effect(old_child_fiber);
+            // console_log!("added deletion effect for {}", old_child_fiber.borrow().element_type());
         }
 
         if let Some(child) = first_child_fiber {
@@ -448,63 +892,90 @@ impl Context {
         }
     }
 
-    fn commit_root(&mut self) -> Result<(), JsValue> {
-        if self.wip_root.is_some() {
-            let wip_root_fiber = self.wip_root.as_ref().unwrap();
+    fn commit_root(&mut self, root: &RootCell) -> Result<(), Error> {
+        let effects = root.borrow_mut().take_effects();
+        let hydration_deletions = root.borrow_mut().hydration_mut()
+            .map(|hydration| hydration.take_deletions())
+            .unwrap_or_default();
 
-            for effect in &self.effects {
-                self.commit_work(&effect)?;
-            }
+        // A commit can't stop halfway, so the first error is only reported once it's finished
+        let mut result = Ok(());
 
-            self.effects.clear();
+        // Existing instances that weren't claimed have no fiber to be deleted with
+        for (parent_instance, instance) in hydration_deletions {
+            result = result.and(self.host().remove_child(&parent_instance.borrow(), &instance));
+        }
 
-            self.current_root = Some(Rc::clone(wip_root_fiber));
-            self.wip_root = None;
+        for effect in &effects {
+            result = result.and(self.commit_work(effect));
+
+            // Reused fibers would otherwise be taken for unplaced ones by later commits
+            effect.borrow_mut().clear_effect_tag();
         }
 
-        Ok(())
+        // Descendants are rendered after their ancestors, so going backwards gets to children first
+        let class_instances = root.borrow_mut().take_wip_class_instances();
+
+        root.borrow_mut().finish_commit();
+
+        if root.borrow().is_unmounting() {
+            self.remove_root(root);
+        }
+
+        // Lifecycle methods run on the committed tree, and may schedule updates on it
+        for instance in class_instances.iter().rev() {
+            result = result.and(instance.commit());
+        }
+
+        Ok(result?)
+    }
+
+    fn remove_root(&mut self, root: &RootCell) {
+        let root_id = root.borrow().id();
+        self.roots.retain(|root| root.borrow().id() != root_id);
+
+        if let Some(current) = root.borrow_mut().take_current() {
+            release_fiber_tree(current);
+        }
     }
 
     fn commit_work(&self, fiber: &FiberCell) -> Result<(), JsValue> {
         match fiber.borrow().effect_tag() {
             Some(FiberEffect::Placement) => {
-                let mut parent_dom_node = None;
-
-                for parent in fiber.parents() {
-                    let parent = parent.borrow();
+                // console_log!("executing PLACEMENT for {}", fiber.borrow().element_type());
+                self.commit_node_placement(fiber)?;
 
-                    if let Some(dom_node) = parent.dom_node() {
-                        parent_dom_node = Some(Rc::clone(dom_node));
-                        break;
-                    }
-                }
+                // A moved fiber may have new props as well
+                let has_props_changed = {
+                    let fiber = fiber.borrow();
+                    let alternate = fiber.alternate().map(|alternate| alternate.borrow());
 
-                // console_log!("executing PLACEMENT for {}", fiber.borrow().element_type());
+                    alternate.as_ref()
+                        .and_then(|alternate| alternate.props())
+                        .is_some_and(|old_props| fiber.has_props_changed(old_props))
+                };
 
-                self.commit_node_append(&fiber, parent_dom_node)?;
+                if has_props_changed {
+                    self.commit_node_update(fiber);
+                }
             },
             Some(FiberEffect::Update) => {
                 // console_log!("executing UPDATE for {}", fiber.borrow().element_type());
-                self.commit_node_update(&fiber)?;
+                self.commit_node_update(fiber);
             },
             Some(FiberEffect::Deletion) => {
                 // console_log!("executing DELETION for {}", fiber.borrow().element_type());
+                // Instances are removed even if a `componentWillUnmount` threw
+                let cleanup = self.commit_deletion_cleanup(fiber);
+                self.commit_node_deletion(fiber)?;
+                cleanup?;
+            },
+            Some(FiberEffect::Hydration) => {
+                let fiber = fiber.borrow();
 
-                // This can break if we have a functional component that returns another functional component
-                // without an element in between. An iterator through all children of a fiber to find
-                // the next DOM element would be fine but for my current case simply getting the first child is quicker.
-                if fiber.borrow().is_functional_tree() {
-                    let fiber_borrowed = fiber.borrow();
-
-                    if let Some(child) = fiber_borrowed.child().as_ref() {
-                        self.commit_node_deletion(&child)?;
-                    } else {
-                        self.commit_node_deletion(&fiber)?;
-                    }
-                } else {
-                    self.commit_node_deletion(&fiber)?;
+                if let (Some(dom_node), Some(props)) = (fiber.dom_node(), fiber.props()) {
+                    self.host().commit_hydration(&dom_node.borrow(), props);
                 }
-
             },
             None => {}
         }
@@ -512,188 +983,2682 @@ impl Context {
         Ok(())
     }
 
-    fn commit_node_append(&self, fiber: &FiberCell, parent_dom_node: Option<Rc<RefCell<Node>>>) -> Result<(), JsValue> {
-        let has_dom_node = fiber.borrow().dom_node().is_some();
-        let has_parent_node = parent_dom_node.is_some();
+    fn commit_node_placement(&self, fiber: &FiberCell) -> Result<(), JsValue> {
+        let parent_instance = match host_parent(fiber) {
+            Some(parent_instance) => parent_instance,
+            None => return Ok(()),
+        };
 
-        if has_dom_node && has_parent_node {
-            let fiber = fiber.borrow();
-            let dom_node = fiber.dom_node().unwrap();
-            let parent_node = parent_dom_node.unwrap();
+        let host = self.host();
+        let parent_instance = &*parent_instance.borrow();
 
-            let dom_node = &*dom_node.borrow();
-            let parent_node = &*parent_node.borrow();
+        // Fibers are placed in order, so anything after this one is either committed already or appended later
+        let before = host_sibling(fiber);
 
-            match (parent_node, dom_node) {
-                // Append HTML element
-                (Node::Element(parent), Node::Element(child)) => {
-                    parent.append_child(&child)?;
-                },
+        // A moved functional fiber takes the instances it rendered along
+        for instance in host_instances(fiber) {
+            let instance = &*instance.borrow();
 
-                // Append text node
-                (Node::Element(parent), Node::Text(text)) => {
-                    parent.append_child(&text)?;
-                }
-                _ => {}
+            match before.as_ref() {
+                Some(before) => host.insert_before(parent_instance, instance, &before.borrow())?,
+                None => host.append_child(parent_instance, instance)?,
             }
         }
 
         Ok(())
     }
 
-    fn commit_node_update(&self, fiber: &FiberCell) -> Result<(), JsValue> {
+    fn commit_node_update(&self, fiber: &FiberCell) {
         let fiber = fiber.borrow();
 
-        if let Some(dom_node) = fiber.dom_node() {
-            if let Some(alternate) = fiber.alternate() {
-                let alternate = alternate.borrow();
-                let prev_props = alternate.props();
-                let next_props = fiber.props().unwrap();
-                let node= &*dom_node.borrow();
-
-                match node {
-                    Node::Element(node) => {
-                        self.update_dom_node(
-                            &node,
-                            prev_props,
-                            next_props
-                        );
-                    },
-                    Node::Text(text) => {
-                        self.update_dom_text(
-                            text,
-                            prev_props,
-                            next_props
-                        );
-                    }
+        if let (Some(dom_node), Some(alternate), Some(next_props)) = (fiber.dom_node(), fiber.alternate(), fiber.props()) {
+            let alternate = alternate.borrow();
+            let prev_props = alternate.props().map(|props| &**props);
+            let instance = &*dom_node.borrow();
+            let host = self.host();
+
+            if fiber.is_text_fiber() {
+                if let Some(next_text) = next_props.node_value() {
+                    let prev_text = prev_props.and_then(|props| props.node_value()).map(String::as_str);
+                    host.commit_text_update(instance, prev_text, next_text);
                 }
+            } else {
+                host.commit_update(instance, prev_props, next_props);
             }
         }
+    }
+
+    fn commit_node_deletion(&self, fiber: &FiberCell) -> Result<(), JsValue> {
+        let parent_instance = match host_parent(fiber) {
+            Some(parent_instance) => parent_instance,
+            None => return Ok(()),
+        };
+
+        let host = self.host();
+
+        // A deleted functional fiber may render any number of host fibers, nested in other functional ones
+        for instance in host_instances(fiber) {
+            host.remove_child(&parent_instance.borrow(), &instance.borrow())?;
+        }
 
         Ok(())
     }
 
-    fn commit_node_deletion(&self, fiber: &FiberCell) -> Result<(), JsValue> {
-        if let Some(dom_node) = fiber.borrow().dom_node() {
-            match &*dom_node.borrow() {
-                Node::Element(node) => {
-                    node.remove();
-                },
-                Node::Text(text) => {
-                    text.remove();
-                }
+    /// Releases what the fibers of a deleted subtree registered outside of it
+    fn commit_deletion_cleanup(&self, fiber: &FiberCell) -> Result<(), JsValue> {
+        let host = self.host();
+        let mut deleted_fibers = vec![Rc::clone(fiber)];
+        let mut result = Ok(());
+
+        // Every instance of the subtree is cleaned up, even after one of them threw
+        while let Some(deleted_fiber) = deleted_fibers.pop() {
+            let deleted_fiber = deleted_fiber.borrow();
+
+            if let Some(instance) = deleted_fiber.class_instance() {
+                result = result.and(instance.unmount());
+            }
+
+            if let (Some(dom_node), Some(props)) = (deleted_fiber.dom_node(), deleted_fiber.props()) {
+                host.detach_instance(&dom_node.borrow(), props);
+            }
+
+            let mut child_fiber = deleted_fiber.child().as_ref().map(Rc::clone);
+
+            while let Some(child) = child_fiber {
+                child_fiber = child.borrow().sibling().as_ref().map(Rc::clone);
+                deleted_fibers.push(child);
             }
         }
 
-        Ok(())
+        result
     }
 }
 
-#[wasm_bindgen]
-pub fn get_context() -> *mut Context {
-    let context = Box::new(Context::new());
+/// Renders the children of a suspended boundary again, once what they waited for settled
+fn retry_suspense_boundary(context_ptr: *mut Context, boundary: &SuspenseBoundary) {
+    // The boundary may have been unmounted in the meantime
+    let (fiber, root) = match (boundary.fiber(), boundary.root()) {
+        (Some(fiber), Some(root)) => (fiber, root),
+        (_, _) => return,
+    };
+
+    let mut context = Context::from_ptr(context_ptr);
+
+    mark_update_lane(&fiber, Lane::Default);
+    root.borrow_mut().schedule_update(Lane::Default);
+    context.ensure_work_scheduled(context_ptr);
+
+    let _ = Box::into_raw(context);
+}
+
+/// Name of the component a fiber was rendered by, if any
+fn owner_component_name(fiber: &Fiber) -> Option<String> {
+    if fiber.is_functional_tree() && !fiber.is_fragment() {
+        return Some(fiber.component_name());
+    }
+
+    let mut parent = fiber.parent().as_ref().map(Rc::clone);
+
+    while let Some(fiber) = parent {
+        let fiber = fiber.borrow();
+
+        if fiber.is_functional_tree() && !fiber.is_fragment() {
+            return Some(fiber.component_name());
+        }
+
+        parent = fiber.parent().as_ref().map(Rc::clone);
+    }
+
+    None
+}
+
+fn perform_scheduled_work(context_ptr: *mut Context, deadline: &dyn Deadline) -> Result<(), Error> {
+    let mut context = Context::from_ptr(context_ptr);
+    context.is_work_scheduled = false;
+
+    let result = context.work_loop(deadline);
+
+    // Other roots keep going after one of them failed
+    if context.has_pending_work() {
+        context.ensure_work_scheduled(context_ptr);
+    }
+
+    let _ = Box::into_raw(context);
+
+    result.map(|_| ())
+}
+
+#[wasm_bindgen(unchecked_return_type = "ContextHandle")]
+pub fn get_context(scheduler_kind: Option<SchedulerKind>) -> *mut Context {
+    let scheduler = scheduler_kind
+        .unwrap_or_else(SchedulerKind::detect)
+        .create_scheduler();
+
+    let context = Box::new(Context::new(scheduler));
     Box::into_raw(context)
 }
 
+#[wasm_bindgen(unchecked_return_type = "RootId")]
+pub fn create_root(
+    #[wasm_bindgen(unchecked_param_type = "ContextHandle")] context_ptr: *mut Context,
+    container: HTMLElement
+) -> u32 {
+    let mut context = Context::from_ptr(context_ptr);
+    let root_id = context.create_dom_root(container).borrow().id();
+
+    let _ = Box::into_raw(context);
+
+    root_id
+}
+
 #[wasm_bindgen]
-pub fn render(context_ptr: *mut Context, element_ptr: *mut Element, container: HTMLElement) -> *mut Context {
+pub fn render_root(
+    #[wasm_bindgen(unchecked_param_type = "ContextHandle")] context_ptr: *mut Context,
+    #[wasm_bindgen(unchecked_param_type = "RootId")] root_id: u32,
+    #[wasm_bindgen(unchecked_param_type = "ElementHandle")] element_ptr: *mut Element
+) {
     let mut context = Context::from_ptr(context_ptr);
     let element = Element::from_ptr(element_ptr);
 
-    // Create the Root fiber
-    let mut root = Fiber::new_root();
-    
-    // The root element will be the Root fiber's only child
-    let mut children = Vec::with_capacity(1);
-    children.push(element);
-    root.set_element_children(Some(Rc::new(RefCell::new(children))));
+    if let Some(root) = context.get_root(root_id) {
+        let mut root = root.borrow_mut();
 
-    // Store the container HTML element
-    root.set_dom_node(Rc::new(RefCell::new(Node::Element(container))));
+        // The root element will be the Root fiber's only child
+        root.set_children(vec![*element]);
+        root.set_unmounting(false);
+        root.schedule_update(context.update_lane);
 
-    // Set the current root as the alternate root
-    if let Some(current_root) = context.current_root.as_ref() {
-        root.set_alternate(Rc::clone(current_root));
+        mem::drop(root);
+        context.ensure_work_scheduled(context_ptr);
     }
 
-    // Make it the Work in Progress Root and the Next Unit of Work
-    let root = Rc::new(RefCell::new(Box::new(root)));
-    context.wip_root = Some(Rc::clone(&root));
-    context.next_unit_of_work = Some(Rc::clone(&root));
+    let _ = Box::into_raw(context);
+}
 
-    Box::into_raw(context)
+/// Deletes everything rendered into the root, which can't be used anymore once that is committed.
+/// The listeners of its instances are removed and its fibers freed, along with their ref hooks.
+/// There are no effect hooks yet, so there are no effect cleanups to run.
+#[wasm_bindgen]
+pub fn unmount(
+    #[wasm_bindgen(unchecked_param_type = "ContextHandle")] context_ptr: *mut Context,
+    #[wasm_bindgen(unchecked_param_type = "RootId")] root_id: u32
+) {
+    let mut context = Context::from_ptr(context_ptr);
+
+    if let Some(root) = context.get_root(root_id) {
+        let mut root = root.borrow_mut();
+
+        root.set_children(Vec::new());
+        root.set_unmounting(true);
+        root.schedule_update(context.update_lane);
+
+        mem::drop(root);
+        context.ensure_work_scheduled(context_ptr);
+    }
+
+    let _ = Box::into_raw(context);
+}
+
+/// Renders into the root mounted on `container`, creating it the first time
+#[wasm_bindgen(unchecked_return_type = "ContextHandle")]
+pub fn render(
+    #[wasm_bindgen(unchecked_param_type = "ContextHandle")] context_ptr: *mut Context,
+    #[wasm_bindgen(unchecked_param_type = "ElementHandle")] element_ptr: *mut Element,
+    container: HTMLElement
+) -> *mut Context {
+    let mut context = Context::from_ptr(context_ptr);
+
+    let root = context.find_root(&container)
+        .unwrap_or_else(|| context.create_dom_root(container));
+    let root_id = root.borrow().id();
+
+    let _ = Box::into_raw(context);
+
+    render_root(context_ptr, root_id, element_ptr);
+
+    context_ptr
+}
+
+/// Renders into the root mounted on `container` like `render`, but takes over the nodes rendered
+/// there by `render_to_string` the first time, attaching their listeners instead of creating them again
+#[wasm_bindgen(unchecked_return_type = "ContextHandle")]
+pub fn hydrate(
+    #[wasm_bindgen(unchecked_param_type = "ContextHandle")] context_ptr: *mut Context,
+    #[wasm_bindgen(unchecked_param_type = "ElementHandle")] element_ptr: *mut Element,
+    container: HTMLElement
+) -> *mut Context {
+    let mut context = Context::from_ptr(context_ptr);
+
+    let root = context.find_root(&container)
+        .unwrap_or_else(|| context.create_dom_root(container));
+
+    if !root.borrow().is_mounted() {
+        root.borrow_mut().start_hydration();
+    }
+
+    let root_id = root.borrow().id();
+
+    let _ = Box::into_raw(context);
+
+    render_root(context_ptr, root_id, element_ptr);
+
+    context_ptr
+}
+
+/// Runs the pending work of a context created with `SchedulerKind::Manual`, throwing what
+/// made a render fail
+#[wasm_bindgen]
+pub fn flush_work(
+    #[wasm_bindgen(unchecked_param_type = "ContextHandle")] context_ptr: *mut Context
+) -> Result<(), JsValue> {
+    Ok(flush_scheduled_work(context_ptr)?)
+}
+
+fn flush_scheduled_work(context_ptr: *mut Context) -> Result<(), Error> {
+    let context = Context::from_ptr(context_ptr);
+    let scheduler = Rc::clone(&context.scheduler);
+    let _ = Box::into_raw(context);
+
+    scheduler.flush()
 }
 
+/// Renders `element` to HTML, running its components once with the initial state of their hooks
+#[wasm_bindgen]
+pub fn render_to_string(
+    #[wasm_bindgen(unchecked_param_type = "ContextHandle")] context_ptr: *mut Context,
+    #[wasm_bindgen(unchecked_param_type = "ElementHandle")] element_ptr: *mut Element
+) -> Result<String, JsValue> {
+    let mut context = Context::from_ptr(context_ptr);
+    let element = Element::from_ptr(element_ptr);
+
+    let html = context.render_to_string(*element);
+
+    let _ = Box::into_raw(context);
+
+    Ok(html?)
+}
+
+/// Renders `element` to HTML like `render_to_string`, but one chunk at a time as `next_chunk` is called
+#[wasm_bindgen]
+pub fn render_to_stream(
+    #[wasm_bindgen(unchecked_param_type = "ContextHandle")] context_ptr: *mut Context,
+    #[wasm_bindgen(unchecked_param_type = "ElementHandle")] element_ptr: *mut Element
+) -> RenderStream {
+    let element = Element::from_ptr(element_ptr);
+
+    RenderStream::new(context_ptr, *element, STREAM_CHUNK_SIZE)
+}
 
 #[wasm_bindgen]
-pub fn work_loop(context_ptr: *mut Context, did_timeout: bool) -> *mut Context {
+pub fn run_with_lane(
+    #[wasm_bindgen(unchecked_param_type = "ContextHandle")] context_ptr: *mut Context,
+    lane: Lane,
+    callback: &js_sys::Function
+) -> Result<JsValue, JsValue> {
     let mut context = Context::from_ptr(context_ptr);
+    let previous_lane = mem::replace(&mut context.update_lane, lane);
+    let _ = Box::into_raw(context);
 
-    context.work_loop(did_timeout).unwrap();
+    // The context is released while the callback runs since setters access it as well
+    let result = callback.call0(&JsValue::null());
 
-    Box::into_raw(context)
+    let mut context = Context::from_ptr(context_ptr);
+    context.update_lane = previous_lane;
+    let _ = Box::into_raw(context);
+
+    result
 }
 
+/// Runs `callback` and renders and commits the updates it made before returning
 #[wasm_bindgen]
-pub fn use_state(context_ptr: *mut Context, initial_value: JsValue) -> Box<[JsValue]> {
-    let context = Context::from_ptr(context_ptr.clone());
-    let wip_fiber = context.wip_functional_fiber.as_ref().unwrap();
+pub fn flush_sync(
+    #[wasm_bindgen(unchecked_param_type = "ContextHandle")] context_ptr: *mut Context,
+    callback: &js_sys::Function
+) -> Result<JsValue, JsValue> {
+    let result = run_with_lane(context_ptr, Lane::Sync, callback);
+
+    let mut context = Context::from_ptr(context_ptr);
+    let sync_result = context.perform_sync_work();
+
+    // Lanes other than the sync one are still waiting
+    if context.has_pending_work() {
+        context.ensure_work_scheduled(context_ptr);
+    }
+
+    let _ = Box::into_raw(context);
+
+    sync_result?;
+    result
+}
+
+/// Hook for the state of the functional fiber being rendered, which it shares with its alternate
+/// so that setters from any render reach the same state
+fn mount_state(context_ptr: *mut Context, kind: HookKind, initial_value: HookState) -> (HookState, HookCell) {
+    let mut context = Context::from_ptr(context_ptr);
+    let wip_fiber = Rc::clone(context.wip_functional_fiber.as_ref().unwrap());
     let mut fiber = wip_fiber.borrow_mut();
+    let hook_idx = fiber.hook_idx() as usize;
 
-    let old_state = fiber.alternate().map_or(None, |alternate| {
+    // Fibers with an alternate take their hooks over, which have to come in the same order
+    let previous_hook = fiber.alternate().map(|alternate| {
         let alternate = alternate.borrow();
-        let hook = alternate.get_hook_at(fiber.hook_idx() as usize);
 
-        hook.map_or(None, |hook| {
-            Some(hook.borrow().clone())
-        })
+        match alternate.get_hook_at(hook_idx) {
+            Some(previous_hook) if previous_hook.borrow().kind() != kind => Err(format!(
+                "called {} as hook #{}, where its previous render called {}. Hooks have to be called in the same order every render.",
+                kind,
+                hook_idx + 1,
+                previous_hook.borrow().kind()
+            )),
+            Some(previous_hook) => Ok(previous_hook),
+            None => Err(format!(
+                "called more hooks than the {} of its previous render. Hooks have to be called in the same order every render, never conditionally or in loops.",
+                alternate.hook_count()
+            )),
+        }
     });
 
-    let current_state = if old_state.is_some() {
-        old_state.unwrap()
-    } else {
-        initial_value
+    // A misused hook gets a fresh one, so the component can return before its render fails
+    let hook = match previous_hook {
+        Some(Ok(previous_hook)) => previous_hook,
+        Some(Err(message)) => {
+            context.report_hook_error(&fiber, &message);
+            Rc::new(RefCell::new(Hook::new(kind, initial_value)))
+        },
+        None => Rc::new(RefCell::new(Hook::new(kind, initial_value))),
     };
 
-    let new_hook = Rc::new(RefCell::new(current_state.clone()));
+    let wip_root = Rc::clone(context.wip_root.as_ref().unwrap());
+    let (current_state, rendered_len) = hook.borrow().render_state(context.render_lane());
+
+    hook.borrow_mut().set_fiber(&wip_fiber);
+    hook.borrow_mut().set_root(&wip_root);
+    fiber.add_hook(Rc::clone(&hook));
+    wip_root.borrow_mut().add_wip_hook(Rc::clone(&hook), rendered_len);
+
+    fiber.incr_hook_idx();
+    mem::drop(fiber);
+
+    let _ = Box::into_raw(context);
+
+    (current_state, hook)
+}
+
+/// Records the first wakeable the component being rendered suspended on
+fn suspend_component(context_ptr: *mut Context, wakeable: Box<dyn Wakeable>) {
+    let mut context = Context::from_ptr(context_ptr);
+
+    // Only the first thing a render suspends on is waited for, like a thrown promise
+    if context.suspended_on.is_none() {
+        context.suspended_on = Some(wakeable);
+    }
+
+    let _ = Box::into_raw(context);
+}
+
+/// Fails the render of the component being rendered once it returns, see `Context::report_hook_error`
+fn report_hook_misuse(context_ptr: *mut Context, message: &str) {
+    let mut context = Context::from_ptr(context_ptr);
+
+    if let Some(fiber) = context.wip_functional_fiber.as_ref().map(Rc::clone) {
+        context.report_hook_error(&fiber.borrow(), message);
+    }
+
+    let _ = Box::into_raw(context);
+}
+
+fn dispatch_state(context_ptr: *mut Context, hook: &HookCell, new_state: HookState) {
+    // Setting the state it already has doesn't need a render
+    if hook.borrow().is_current_state(&new_state) {
+        return;
+    }
+
+    let mut context = Context::from_ptr(context_ptr);
+    let lane = context.update_lane;
+
+    hook.borrow_mut().enqueue(lane, new_state);
+
+    if let Some(fiber) = hook.borrow().fiber() {
+        mark_update_lane(&fiber, lane);
+    }
+
+    if let Some(root) = hook.borrow().root() {
+        root.borrow_mut().schedule_update(lane);
+        context.ensure_work_scheduled(context_ptr);
+    }
+
+    let _ = Box::into_raw(context);
+}
+
+#[wasm_bindgen(unchecked_return_type = "StateTuple")]
+pub fn use_state(
+    #[wasm_bindgen(unchecked_param_type = "ContextHandle")] context_ptr: *mut Context,
+    initial_value: JsValue
+) -> Box<[JsValue]> {
+    let (current_state, hook) = mount_state(context_ptr, HookKind::State, HookState::Js(initial_value));
 
-    fiber.add_hook(Rc::clone(&new_hook));
+    let current_state = match current_state {
+        HookState::Js(value) => value,
+        HookState::Rust(_) => JsValue::undefined(),
+    };
 
     let set_state = Closure::wrap(Box::new(move |new_state: JsValue| {
-        *new_hook.borrow_mut() = new_state;
+        dispatch_state(context_ptr, &hook, HookState::Js(new_state));
+    }) as Box<dyn FnMut(JsValue)>).into_js_value();
+
+    vec![current_state, set_state].into_boxed_slice()
+}
+
+/// `useRef`, an object whose `current` property is kept for as long as the component is mounted
+#[wasm_bindgen(unchecked_return_type = "{ current: any }")]
+pub fn use_ref(
+    #[wasm_bindgen(unchecked_param_type = "ContextHandle")] context_ptr: *mut Context,
+    initial_value: JsValue
+) -> JsValue {
+    let ref_object = js_sys::Object::new();
+    let _ = js_sys::Reflect::set(&ref_object, &JsValue::from_str("current"), &initial_value);
+
+    match mount_state(context_ptr, HookKind::Ref, HookState::Js(ref_object.into())).0 {
+        HookState::Js(ref_object) => ref_object,
+        HookState::Rust(_) => JsValue::undefined(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use scheduler::ManualScheduler;
+    use test_renderer::{TestHost, TestInstance, element, text};
+
+    fn test_context() -> *mut Context {
+        // Warnings tests expect are collected with `collect_warnings`
+        set_warning_sink(Box::new(|_| {}));
+        Box::into_raw(Box::new(Context::new(Rc::new(ManualScheduler::default()))))
+    }
+
+    fn create_test_root(context_ptr: *mut Context) -> (u32, TestInstance) {
+        let container = TestInstance::container();
+
         let mut context = Context::from_ptr(context_ptr);
+        let root_id = context.create_root(Node::new(container.clone()), Rc::new(TestHost)).borrow().id();
+        let _ = Box::into_raw(context);
 
-        let current_root = context.current_root.as_ref();
-        let mut root = Fiber::new_root();
-        
-        current_root.map(|current_root| {
-            root.set_alternate(Rc::clone(current_root));
+        (root_id, container)
+    }
 
-            let current_root = current_root.borrow();
+    /// Root over a container that already has what `element` renders, as a server would have sent it
+    fn create_hydrating_root(context_ptr: *mut Context, element: Box<Element>) -> (u32, TestInstance) {
+        let server_context_ptr = test_context();
+        let (server_root_id, container) = create_test_root(server_context_ptr);
+        render_and_flush(server_context_ptr, server_root_id, element);
 
-            if let Some(children) = current_root.element_children().as_ref() {
-                root.set_element_children(Some(Rc::clone(&children)));
-            }
+        let mut context = Context::from_ptr(context_ptr);
+        let root = context.create_root(Node::new(container.clone()), Rc::new(TestHost));
+        root.borrow_mut().start_hydration();
+        let root_id = root.borrow().id();
+        let _ = Box::into_raw(context);
+
+        (root_id, container)
+    }
+
+    /// Warnings reported from now on this thread
+    fn collect_warnings() -> Rc<RefCell<Vec<String>>> {
+        let warnings = Rc::new(RefCell::new(Vec::new()));
+        let sink = Rc::clone(&warnings);
+        set_warning_sink(Box::new(move |message| sink.borrow_mut().push(String::from(message))));
+
+        warnings
+    }
+
+    fn render_and_flush(context_ptr: *mut Context, root_id: u32, element: Box<Element>) {
+        render_root(context_ptr, root_id, Box::into_raw(element));
+        flush_scheduled_work(context_ptr).unwrap();
+    }
+
+    /// Lets the work loop check the deadline a number of times before yielding
+    struct YieldAfter(Cell<usize>);
+
+    impl Deadline for YieldAfter {
+        fn should_yield(&self) -> bool {
+            let remaining = self.0.get();
+            self.0.set(remaining.saturating_sub(1));
 
-            // Store the container HTML element
-            if let Some(dom_node) = current_root.dom_node().as_ref() {
-                root.set_dom_node(Rc::clone(dom_node));
+            remaining == 0
+        }
+    }
+
+    #[test]
+    fn lanes_are_rendered_from_the_most_urgent_one() {
+        let mut lanes = Lanes::default();
+        assert_eq!(lanes.highest_priority(), None);
+
+        lanes.insert(Lane::Transition);
+        lanes.insert(Lane::Discrete);
+        assert_eq!(lanes.highest_priority(), Some(Lane::Discrete));
+
+        lanes.remove(Lane::Discrete);
+        assert_eq!(lanes.highest_priority(), Some(Lane::Transition));
+
+        assert!(Lane::Discrete.is_included_in(Lane::Default));
+        assert!(!Lane::Transition.is_included_in(Lane::Default));
+    }
+
+    #[test]
+    fn manual_scheduler_runs_tasks_scheduled_while_flushing() {
+        let scheduler = SchedulerKind::Manual.create_scheduler();
+        let ran = Rc::new(RefCell::new(Vec::new()));
+
+        let first = {
+            let scheduler = Rc::clone(&scheduler);
+            let ran = Rc::clone(&ran);
+
+            move |deadline: &dyn Deadline| {
+                ran.borrow_mut().push(("first", deadline.should_yield()));
+
+                let ran = Rc::clone(&ran);
+                scheduler.schedule(Box::new(move |deadline| {
+                    ran.borrow_mut().push(("third", deadline.should_yield()));
+                    Ok(())
+                }));
+
+                Ok(())
             }
-        });
+        };
+
+        scheduler.schedule(Box::new(first));
+        scheduler.schedule(Box::new({
+            let ran = Rc::clone(&ran);
+            move |deadline| {
+                ran.borrow_mut().push(("second", deadline.should_yield()));
+                Ok(())
+            }
+        }));
+        assert!(ran.borrow().is_empty());
 
-        // Make it the Work in Progress Root and the Next Unit of Work
-        let root = Rc::new(RefCell::new(Box::new(root)));
-        context.wip_root = Some(Rc::clone(&root));
-        context.next_unit_of_work = Some(Rc::clone(&root));
+        // Flushed tasks run to completion, in the order they were scheduled
+        assert!(scheduler.flush().is_ok());
+        assert_eq!(*ran.borrow(), vec![("first", false), ("second", false), ("third", false)]);
+    }
 
-        Box::into_raw(context);
-    }) as Box<dyn FnMut(JsValue)>).into_js_value();
+    /// Host config logging the calls the reconciler makes to it, with instances that are
+    /// named after their element type or text
+    struct LoggingHost {
+        calls: Rc<RefCell<Vec<String>>>,
+    }
 
-    fiber.incr_hook_idx();
-    mem::drop(fiber);
-    mem::drop(wip_fiber);
+    impl LoggingHost {
+        fn log(&self, call: String) {
+            self.calls.borrow_mut().push(call);
+        }
 
-    Box::into_raw(context);
+        fn name(instance: &Node) -> &str {
+            instance.downcast_ref::<String>().unwrap()
+        }
+    }
 
-    vec![current_state, set_state].into_boxed_slice()
+    impl HostConfig for LoggingHost {
+        fn create_instance(&self, element_type: &str, _props: &ElementProps) -> Node {
+            self.log(format!("create {}", element_type));
+            Node::new(String::from(element_type))
+        }
+
+        fn create_text_instance(&self, text: &str) -> Node {
+            self.log(format!("create \"{}\"", text));
+            Node::new(format!("\"{}\"", text))
+        }
+
+        fn append_child(&self, parent: &Node, child: &Node) -> Result<(), JsValue> {
+            self.log(format!("append {} to {}", Self::name(child), Self::name(parent)));
+            Ok(())
+        }
+
+        fn insert_before(&self, parent: &Node, child: &Node, before: &Node) -> Result<(), JsValue> {
+            self.log(format!("insert {} into {} before {}", Self::name(child), Self::name(parent), Self::name(before)));
+            Ok(())
+        }
+
+        fn remove_child(&self, parent: &Node, child: &Node) -> Result<(), JsValue> {
+            self.log(format!("remove {} from {}", Self::name(child), Self::name(parent)));
+            Ok(())
+        }
+
+        fn commit_update(&self, instance: &Node, _prev_props: Option<&ElementProps>, _next_props: &ElementProps) {
+            self.log(format!("update {}", Self::name(instance)));
+        }
+
+        fn commit_text_update(&self, instance: &Node, _prev_text: Option<&str>, next_text: &str) {
+            self.log(format!("update {} to \"{}\"", Self::name(instance), next_text));
+        }
+    }
+
+    #[test]
+    fn reconciler_renders_through_the_host_config() {
+        let scheduler = SchedulerKind::Manual.create_scheduler();
+        let context_ptr = Box::into_raw(Box::new(Context::new(scheduler)));
+        let calls = Rc::new(RefCell::new(Vec::new()));
+
+        let mut context = Context::from_ptr(context_ptr);
+        let host = LoggingHost { calls: Rc::clone(&calls) };
+        let root_id = context.create_root(Node::new(String::from("container")), Rc::new(host)).borrow().id();
+        let _ = Box::into_raw(context);
+
+        let render_and_flush = |element| {
+            render_root(context_ptr, root_id, Box::into_raw(element));
+            flush_scheduled_work(context_ptr).unwrap();
+
+            calls.borrow_mut().drain(..).collect::<Vec<String>>()
+        };
+
+        // Instances are created while rendering, and only attached once it's committed
+        assert_eq!(
+            render_and_flush(element("div", Some("before"), vec![element("p", None, vec![text("a")])])),
+            vec!["create div", "create p", "create \"a\"", "append div to container", "append p to div", "append \"a\" to p"]
+        );
+
+        assert_eq!(
+            render_and_flush(element("div", Some("after"), vec![element("p", None, vec![text("b")])])),
+            vec!["update div", "update \"a\" to \"b\""]
+        );
+
+        assert_eq!(render_and_flush(element("div", Some("after"), vec![])), vec!["remove p from div"]);
+    }
+
+    #[test]
+    fn mounts_host_elements_and_text() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+
+        render_and_flush(context_ptr, root_id, element("div", Some("app"), vec![
+            element("span", None, vec![text("hello")]),
+            text(" world"),
+        ]));
+
+        assert_eq!(container.inner_html(), "<div class=\"app\"><span>hello</span> world</div>");
+    }
+
+    #[test]
+    fn updates_props_and_text_in_place() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+
+        render_and_flush(context_ptr, root_id, element("div", Some("before"), vec![text("one")]));
+        let div = container.children().remove(0);
+        let div_text = div.children().remove(0);
+
+        render_and_flush(context_ptr, root_id, element("div", Some("after"), vec![text("two")]));
+
+        assert_eq!(container.inner_html(), "<div class=\"after\">two</div>");
+        assert!(container.children()[0].is_same(&div));
+        assert!(div.children()[0].is_same(&div_text));
+    }
+
+    #[test]
+    fn appends_and_removes_trailing_children() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+
+        render_and_flush(context_ptr, root_id, element("ul", None, vec![
+            element("li", None, vec![text("a")]),
+        ]));
+
+        render_and_flush(context_ptr, root_id, element("ul", None, vec![
+            element("li", None, vec![text("a")]),
+            element("li", None, vec![text("b")]),
+            element("li", None, vec![text("c")]),
+        ]));
+
+        assert_eq!(container.inner_html(), "<ul><li>a</li><li>b</li><li>c</li></ul>");
+
+        render_and_flush(context_ptr, root_id, element("ul", None, vec![
+            element("li", None, vec![text("a")]),
+        ]));
+
+        assert_eq!(container.inner_html(), "<ul><li>a</li></ul>");
+    }
+
+    #[test]
+    fn replaces_children_of_another_type() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            element("p", None, vec![text("paragraph")]),
+        ]));
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            element("h1", None, vec![text("title")]),
+        ]));
+
+        assert_eq!(container.inner_html(), "<div><h1>title</h1></div>");
+    }
+
+    #[test]
+    fn inserts_placed_fibers_before_committed_siblings() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            element("p", None, vec![]),
+            element("i", None, vec![]),
+        ]));
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            element("span", None, vec![]),
+            element("i", None, vec![]),
+        ]));
+
+        assert_eq!(container.inner_html(), "<div><span></span><i></i></div>");
+    }
+
+    #[test]
+    fn unmount_removes_the_tree_and_the_root() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![text("mounted")]));
+
+        unmount(context_ptr, root_id);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        let context = Context::from_ptr(context_ptr);
+        assert!(context.get_root(root_id).is_none());
+        let _ = Box::into_raw(context);
+
+        assert_eq!(container.inner_html(), "");
+    }
+
+    #[test]
+    fn roots_render_independently() {
+        let context_ptr = test_context();
+        let (first_id, first) = create_test_root(context_ptr);
+        let (second_id, second) = create_test_root(context_ptr);
+
+        render_and_flush(context_ptr, first_id, element("p", None, vec![text("first")]));
+        render_and_flush(context_ptr, second_id, element("p", None, vec![text("second")]));
+
+        unmount(context_ptr, first_id);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(first.inner_html(), "");
+        assert_eq!(second.inner_html(), "<p>second</p>");
+    }
+
+    #[test]
+    fn work_loop_commits_once_the_render_completes() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+
+        render_root(context_ptr, root_id, Box::into_raw(element("ul", None, vec![
+            element("li", None, vec![text("a")]),
+            element("li", None, vec![text("b")]),
+        ])));
+
+        let mut context = Context::from_ptr(context_ptr);
+
+        // Nothing is committed while the render is still in progress
+        assert_eq!(context.work_loop(&YieldAfter(Cell::new(3))).ok(), Some(true));
+        assert_eq!(container.inner_html(), "");
+
+        assert_eq!(context.work_loop(&Unbounded).ok(), Some(false));
+        assert_eq!(container.inner_html(), "<ul><li>a</li><li>b</li></ul>");
+
+        let _ = Box::into_raw(context);
+    }
+
+    #[test]
+    fn work_loop_resumes_where_the_last_deadline_stopped_it() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+
+        render_root(context_ptr, root_id, Box::into_raw(element("ul", None, vec![
+            element("li", None, vec![text("a")]),
+            element("li", None, vec![text("b")]),
+            element("li", None, vec![text("c")]),
+        ])));
+
+        let mut context = Context::from_ptr(context_ptr);
+        let mut yielded = 0;
+
+        // Each deadline leaves time for a single unit of work, so every fiber but the last one yields
+        while context.work_loop(&YieldAfter(Cell::new(2))).ok() == Some(true) {
+            assert_eq!(container.inner_html(), "");
+            yielded += 1;
+        }
+
+        assert_eq!(yielded, 7);
+        assert_eq!(container.inner_html(), "<ul><li>a</li><li>b</li><li>c</li></ul>");
+
+        // Nothing left to do
+        assert_eq!(context.work_loop(&Unbounded).ok(), Some(false));
+
+        let _ = Box::into_raw(context);
+    }
+
+    #[test]
+    fn scheduled_work_reschedules_itself_only_while_work_remains() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+
+        render_root(context_ptr, root_id, Box::into_raw(element("p", None, vec![text("deadline")])));
+
+        let is_work_scheduled = |context_ptr: *mut Context| {
+            let context = Context::from_ptr(context_ptr);
+            let is_work_scheduled = context.is_work_scheduled;
+            let _ = Box::into_raw(context);
+
+            is_work_scheduled
+        };
+
+        perform_scheduled_work(context_ptr, &YieldAfter(Cell::new(2))).unwrap();
+        assert!(is_work_scheduled(context_ptr));
+
+        perform_scheduled_work(context_ptr, &Unbounded).unwrap();
+        assert!(!is_work_scheduled(context_ptr));
+        assert_eq!(container.inner_html(), "<p>deadline</p>");
+    }
+
+    /// Manual scheduler counting the tasks scheduled on it
+    #[derive(Default)]
+    struct CountingScheduler {
+        scheduled: Cell<u32>,
+        manual: ManualScheduler,
+    }
+
+    impl Scheduler for CountingScheduler {
+        fn schedule(&self, task: scheduler::Task) {
+            self.scheduled.set(self.scheduled.get() + 1);
+            self.manual.schedule(task);
+        }
+
+        fn flush(&self) -> Result<(), Error> {
+            self.manual.flush()
+        }
+    }
+
+    #[test]
+    fn context_schedules_a_single_task_for_its_pending_work() {
+        let scheduler = Rc::new(CountingScheduler::default());
+        let context_ptr = Box::into_raw(Box::new(Context::new(Rc::clone(&scheduler) as Rc<dyn Scheduler>)));
+        let (first_id, first) = create_test_root(context_ptr);
+        let (second_id, second) = create_test_root(context_ptr);
+
+        render_root(context_ptr, first_id, Box::into_raw(element("p", None, vec![text("first")])));
+        render_root(context_ptr, second_id, Box::into_raw(element("p", None, vec![text("second")])));
+
+        // Nothing runs until the scheduler does
+        assert_eq!(scheduler.scheduled.get(), 1);
+        assert_eq!(first.inner_html(), "");
+
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(first.inner_html(), "<p>first</p>");
+        assert_eq!(second.inner_html(), "<p>second</p>");
+
+        render_root(context_ptr, first_id, Box::into_raw(element("p", None, vec![text("again")])));
+
+        assert_eq!(scheduler.scheduled.get(), 2);
+    }
+
+    #[test]
+    fn the_root_with_the_most_urgent_update_renders_first() {
+        let context_ptr = test_context();
+        let (transition_id, transition) = create_test_root(context_ptr);
+        let (default_id, default) = create_test_root(context_ptr);
+
+        let mut context = Context::from_ptr(context_ptr);
+        context.update_lane = Lane::Transition;
+        let _ = Box::into_raw(context);
+
+        render_root(context_ptr, transition_id, Box::into_raw(element("p", None, vec![text("transition")])));
+
+        let mut context = Context::from_ptr(context_ptr);
+        context.update_lane = Lane::Default;
+        let _ = Box::into_raw(context);
+
+        render_root(context_ptr, default_id, Box::into_raw(element("p", None, vec![text("default")])));
+
+        let mut context = Context::from_ptr(context_ptr);
+
+        // Just enough time for the three fibers of the default root
+        assert_eq!(context.work_loop(&YieldAfter(Cell::new(4))).ok(), Some(true));
+        assert_eq!(default.inner_html(), "<p>default</p>");
+        assert_eq!(transition.inner_html(), "");
+
+        assert_eq!(context.work_loop(&Unbounded).ok(), Some(false));
+        assert_eq!(transition.inner_html(), "<p>transition</p>");
+
+        let _ = Box::into_raw(context);
+    }
+
+    #[test]
+    fn more_urgent_updates_interrupt_the_render_in_progress() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+
+        let mut context = Context::from_ptr(context_ptr);
+        context.update_lane = Lane::Transition;
+        let _ = Box::into_raw(context);
+
+        render_root(context_ptr, root_id, Box::into_raw(element("p", None, vec![text("transition")])));
+
+        let mut context = Context::from_ptr(context_ptr);
+        assert_eq!(context.work_loop(&YieldAfter(Cell::new(2))).ok(), Some(true));
+        assert!(context.get_root(root_id).unwrap().borrow().wip().is_some());
+        context.update_lane = Lane::Default;
+        let _ = Box::into_raw(context);
+
+        render_root(context_ptr, root_id, Box::into_raw(element("p", None, vec![text("default")])));
+
+        let context = Context::from_ptr(context_ptr);
+        assert!(context.get_root(root_id).unwrap().borrow().wip().is_none());
+        let _ = Box::into_raw(context);
+
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(container.inner_html(), "<p>default</p>");
+    }
+
+    #[test]
+    fn sync_work_is_committed_without_waiting_for_the_scheduler() {
+        let context_ptr = test_context();
+        let (sync_id, sync_container) = create_test_root(context_ptr);
+        let (default_id, default_container) = create_test_root(context_ptr);
+
+        render_root(context_ptr, default_id, Box::into_raw(element("p", None, vec![text("default")])));
+
+        let mut context = Context::from_ptr(context_ptr);
+        context.update_lane = Lane::Sync;
+        let _ = Box::into_raw(context);
+
+        render_root(context_ptr, sync_id, Box::into_raw(element("p", None, vec![text("sync")])));
+
+        let mut context = Context::from_ptr(context_ptr);
+        context.update_lane = Lane::Default;
+        context.perform_sync_work().unwrap();
+
+        // Other roots are left to the scheduler
+        assert_eq!(sync_container.inner_html(), "<p>sync</p>");
+        assert_eq!(default_container.inner_html(), "");
+        assert!(context.has_pending_work());
+        let _ = Box::into_raw(context);
+
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(default_container.inner_html(), "<p>default</p>");
+    }
+
+    #[test]
+    fn unmount_detaches_every_instance() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            element("p", None, vec![text("mounted")]),
+        ]));
+
+        let div = container.children().remove(0);
+        let p = div.children().remove(0);
+        assert!(!div.is_detached() && !p.is_detached());
+
+        unmount(context_ptr, root_id);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        // As the DOM host removes their listeners
+        assert!(div.is_detached());
+        assert!(p.is_detached());
+    }
+
+    #[test]
+    fn renders_to_string() {
+        let context_ptr = test_context();
+        let mut context = Context::from_ptr(context_ptr);
+
+        let html = context.render_to_string(*element("div", Some("app"), vec![
+            element("h1", None, vec![text("Tom & \"Jerry\"")]),
+            element("input", Some("new-todo"), vec![]),
+            text("<one>"),
+            text("two"),
+        ]));
+
+        assert_eq!(
+            html.ok().as_deref(),
+            Some("<div class=\"app\"><h1>Tom &amp; \"Jerry\"</h1><input class=\"new-todo\">&lt;one&gt;<!-- -->two</div>")
+        );
+        assert!(context.roots.is_empty());
+
+        let _ = Box::into_raw(context);
+    }
+
+    #[test]
+    fn streams_chunks_as_host_subtrees_complete() {
+        let context_ptr = test_context();
+        let tree = || element("ul", None, vec![
+            element("li", None, vec![text("a")]),
+            element("li", None, vec![text("b")]),
+        ]);
+
+        let chunks = RenderStream::new(context_ptr, *tree(), 1).collect::<Result<Vec<String>, Error>>().unwrap();
+        assert_eq!(chunks, vec!["<ul><li>a</li>", "<li>b</li>", "</ul>"]);
+
+        let html = RenderStream::new(context_ptr, *tree(), STREAM_CHUNK_SIZE).collect::<Result<String, Error>>().unwrap();
+        let mut context = Context::from_ptr(context_ptr);
+        assert_eq!(context.render_to_string(*tree()).ok(), Some(html));
+        let _ = Box::into_raw(context);
+    }
+
+    #[test]
+    fn hydrates_existing_instances() {
+        let context_ptr = test_context();
+        let tree = || element("div", Some("app"), vec![
+            element("p", None, vec![text("one"), text("two")]),
+        ]);
+
+        let (root_id, container) = create_hydrating_root(context_ptr, tree());
+        let div = container.children().remove(0);
+        let p = div.children().remove(0);
+        let texts = p.children();
+
+        render_and_flush(context_ptr, root_id, tree());
+
+        assert_eq!(container.inner_html(), "<div class=\"app\"><p>onetwo</p></div>");
+        assert!(container.children()[0].is_same(&div));
+        assert!(div.children()[0].is_same(&p));
+        assert!(p.children().iter().zip(&texts).all(|(text, server_text)| text.is_same(server_text)));
+    }
+
+    #[test]
+    fn hydration_recreates_mismatched_subtrees() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_hydrating_root(context_ptr, element("div", None, vec![
+            element("p", None, vec![text("server")]),
+            element("i", None, vec![text("same")]),
+            element("b", None, vec![]),
+        ]));
+
+        let div = container.children().remove(0);
+        let i = div.children().remove(1);
+        let warnings = collect_warnings();
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            element("h1", None, vec![text("client")]),
+            element("i", None, vec![text("same")]),
+        ]));
+
+        assert_eq!(container.inner_html(), "<div><h1>client</h1><i>same</i></div>");
+        assert!(container.children()[0].is_same(&div));
+        assert!(div.children()[1].is_same(&i));
+        assert_eq!(*warnings.borrow(), vec![
+            "Reactron: expected <h1> but the server HTML has <p>server</p>, so it is created again",
+            "Reactron: the server HTML has an extra <b></b>, so it is removed",
+        ]);
+    }
+
+    #[test]
+    fn hydration_recreates_mismatched_text() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_hydrating_root(context_ptr, element("p", None, vec![text("server")]));
+
+        render_and_flush(context_ptr, root_id, element("p", None, vec![text("client"), text("!")]));
+
+        assert_eq!(container.inner_html(), "<p>client!</p>");
+    }
+
+    struct Greeting {
+        name: String,
+    }
+
+    impl Component for Greeting {
+        fn render(&self, _hooks: &mut Hooks) -> Option<Box<Element>> {
+            Some(element("h1", None, vec![text(&format!("Hello, {}", self.name))]))
+        }
+    }
+
+    #[test]
+    fn renders_rust_components_with_state() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let set_count: Rc<RefCell<Option<SetState<u32>>>> = Rc::new(RefCell::new(None));
+        let renders = Rc::new(Cell::new(0));
+
+        let counter = {
+            let set_count = Rc::clone(&set_count);
+            let renders = Rc::clone(&renders);
+
+            move |hooks: &mut Hooks| {
+                let (count, set) = hooks.use_state(0u32);
+                set_count.replace(Some(set));
+                renders.set(renders.get() + 1);
+
+                Some(element("p", None, vec![text(&count.to_string())]))
+            }
+        };
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            Box::new(Element::from_component(Greeting { name: String::from("Rust") })),
+            Box::new(Element::from_component(counter)),
+        ]));
+
+        assert_eq!(container.inner_html(), "<div><h1>Hello, Rust</h1><p>0</p></div>");
+
+        set_count.borrow().as_ref().unwrap().set(1);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(container.inner_html(), "<div><h1>Hello, Rust</h1><p>1</p></div>");
+        assert_eq!(renders.get(), 2);
+
+        // Setting the state it already has doesn't render again
+        set_count.borrow().as_ref().unwrap().set(1);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(renders.get(), 2);
+    }
+
+    fn counter(set_count: &Rc<RefCell<Option<SetState<u32>>>>, renders: &Rc<Cell<u32>>) -> Box<Element> {
+        let set_count = Rc::clone(set_count);
+        let renders = Rc::clone(renders);
+
+        Box::new(Element::from_component(move |hooks: &mut Hooks| {
+            let (count, set) = hooks.use_state(0u32);
+            set_count.replace(Some(set));
+            renders.set(renders.get() + 1);
+
+            Some(element("p", None, vec![text(&count.to_string())]))
+        }))
+    }
+
+    fn set_update_lane(context_ptr: *mut Context, lane: Lane) {
+        let mut context = Context::from_ptr(context_ptr);
+        context.update_lane = lane;
+        let _ = Box::into_raw(context);
+    }
+
+    #[test]
+    fn less_urgent_updates_are_left_for_a_later_render() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let set_first: Rc<RefCell<Option<SetState<u32>>>> = Rc::new(RefCell::new(None));
+        let set_second: Rc<RefCell<Option<SetState<u32>>>> = Rc::new(RefCell::new(None));
+        let rendered = Rc::new(RefCell::new(Vec::new()));
+
+        let pair = {
+            let set_first = Rc::clone(&set_first);
+            let set_second = Rc::clone(&set_second);
+            let rendered = Rc::clone(&rendered);
+
+            move |hooks: &mut Hooks| {
+                let (first, set) = hooks.use_state(0u32);
+                set_first.replace(Some(set));
+                let (second, set) = hooks.use_state(0u32);
+                set_second.replace(Some(set));
+                rendered.borrow_mut().push((first, second));
+
+                Some(element("p", None, vec![text(&format!("{} {}", first, second))]))
+            }
+        };
+
+        render_and_flush(context_ptr, root_id, Box::new(Element::from_component(pair)));
+
+        set_update_lane(context_ptr, Lane::Transition);
+        set_first.borrow().as_ref().unwrap().set(1);
+        set_update_lane(context_ptr, Lane::Default);
+        set_second.borrow().as_ref().unwrap().set(1);
+
+        flush_scheduled_work(context_ptr).unwrap();
+
+        // The default update is rendered first, without the transition made before it
+        assert_eq!(*rendered.borrow(), vec![(0, 0), (0, 1), (1, 1)]);
+        assert_eq!(container.inner_html(), "<p>1 1</p>");
+    }
+
+    #[test]
+    fn children_of_an_interrupted_render_are_rendered_by_the_next_one() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let set_count: Rc<RefCell<Option<SetState<u32>>>> = Rc::new(RefCell::new(None));
+
+        let counter = {
+            let set_count = Rc::clone(&set_count);
+
+            move |hooks: &mut Hooks| {
+                let (count, set) = hooks.use_state(0u32);
+                set_count.replace(Some(set));
+
+                Some(element("p", None, vec![text(&count.to_string())]))
+            }
+        };
+
+        let tree = |label: &str| Box::into_raw(element("div", None, vec![
+            Box::new(Element::from_component(counter.clone())),
+            element("span", None, vec![text(label)]),
+        ]));
+
+        render_root(context_ptr, root_id, tree("a"));
+        flush_scheduled_work(context_ptr).unwrap();
+
+        set_update_lane(context_ptr, Lane::Transition);
+        render_root(context_ptr, root_id, tree("b"));
+        set_update_lane(context_ptr, Lane::Default);
+
+        let mut context = Context::from_ptr(context_ptr);
+        assert_eq!(context.work_loop(&YieldAfter(Cell::new(2))).ok(), Some(true));
+        let _ = Box::into_raw(context);
+
+        // Interrupts the transition, and renders the children it took instead
+        set_update_lane(context_ptr, Lane::Sync);
+        set_count.borrow().as_ref().unwrap().set(1);
+        set_update_lane(context_ptr, Lane::Default);
+
+        let mut context = Context::from_ptr(context_ptr);
+        assert!(context.perform_sync_work().is_ok());
+        let _ = Box::into_raw(context);
+
+        assert_eq!(container.inner_html(), "<div><p>1</p><span>b</span></div>");
+
+        // Renders after that render them again, as the current tree's
+        set_count.borrow().as_ref().unwrap().set(2);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(container.inner_html(), "<div><p>2</p><span>b</span></div>");
+    }
+
+    #[test]
+    fn updates_made_together_are_rendered_once() {
+        let scheduler = Rc::new(CountingScheduler::default());
+        let context_ptr = Box::into_raw(Box::new(Context::new(Rc::clone(&scheduler) as Rc<dyn Scheduler>)));
+        let (root_id, container) = create_test_root(context_ptr);
+        let set_first = Rc::new(RefCell::new(None));
+        let set_second = Rc::new(RefCell::new(None));
+        let renders = Rc::new(Cell::new(0));
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            counter(&set_first, &renders),
+            counter(&set_second, &renders),
+        ]));
+
+        // As an event handler would
+        set_first.borrow().as_ref().unwrap().set(1);
+        set_second.borrow().as_ref().unwrap().set(1);
+        set_second.borrow().as_ref().unwrap().set(2);
+
+        assert_eq!(scheduler.scheduled.get(), 2);
+
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(container.inner_html(), "<div><p>1</p><p>2</p></div>");
+        assert_eq!(renders.get(), 4);
+    }
+
+    #[test]
+    fn hooks_keep_their_state_per_component_and_call_order() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let set_count = Rc::new(RefCell::new(None));
+        let set_label: Rc<RefCell<Option<SetState<&'static str>>>> = Rc::new(RefCell::new(None));
+        let set_other = Rc::new(RefCell::new(None));
+        let renders = Rc::new(Cell::new(0));
+
+        let labelled = {
+            let set_count = Rc::clone(&set_count);
+            let set_label = Rc::clone(&set_label);
+
+            move |hooks: &mut Hooks| {
+                let (label, set) = hooks.use_state("count");
+                set_label.replace(Some(set));
+                let (count, set) = hooks.use_state(0u32);
+                set_count.replace(Some(set));
+
+                Some(element("p", None, vec![text(&format!("{}: {}", label, count))]))
+            }
+        };
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            Box::new(Element::from_component(labelled)),
+            counter(&set_other, &renders),
+        ]));
+
+        assert_eq!(container.inner_html(), "<div><p>count: 0</p><p>0</p></div>");
+
+        set_count.borrow().as_ref().unwrap().set(2);
+        set_label.borrow().as_ref().unwrap().set("total");
+        flush_scheduled_work(context_ptr).unwrap();
+
+        // Each hook keeps its own state, and other components' state isn't touched
+        assert_eq!(container.inner_html(), "<div><p>total: 2</p><p>0</p></div>");
+        assert_eq!(renders.get(), 1);
+
+        set_other.borrow().as_ref().unwrap().set(5);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(container.inner_html(), "<div><p>total: 2</p><p>5</p></div>");
+    }
+
+    #[test]
+    fn refs_keep_their_value_without_rendering_again() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let set_count = Rc::new(RefCell::new(None));
+        let renders = Rc::new(RefCell::new(None));
+
+        let component = {
+            let set_count = Rc::clone(&set_count);
+            let renders = Rc::clone(&renders);
+
+            move |hooks: &mut Hooks| {
+                let (count, set) = hooks.use_state(0u32);
+                let rendered = hooks.use_ref(0u32);
+                *rendered.borrow_mut() += 1;
+
+                set_count.replace(Some(set));
+                renders.replace(Some(rendered));
+
+                Some(element("p", None, vec![text(&count.to_string())]))
+            }
+        };
+
+        render_and_flush(context_ptr, root_id, Box::new(Element::from_component(component)));
+
+        set_count.borrow().as_ref().unwrap().set(1);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        let rendered = renders.borrow().as_ref().map(Rc::clone).unwrap();
+        assert_eq!(*rendered.borrow(), 2);
+
+        // Changing it doesn't render the component
+        *rendered.borrow_mut() = 10;
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(container.inner_html(), "<p>1</p>");
+        assert_eq!(*rendered.borrow(), 10);
+    }
+
+    #[test]
+    fn hooks_called_out_of_order_fail_the_render() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let hooks_called = Rc::new(Cell::new("state"));
+
+        let component = {
+            let hooks_called = Rc::clone(&hooks_called);
+
+            move |hooks: &mut Hooks| {
+                let count = match hooks_called.get() {
+                    "none" => 0,
+                    "ref" => *hooks.use_ref(0u32).borrow(),
+                    "text state" => hooks.use_state(String::from("0")).0.len() as u32,
+                    "two states" => hooks.use_state(0u32).0 + hooks.use_state(0u32).0,
+                    _ => hooks.use_state(0u32).0,
+                };
+
+                Some(element("p", None, vec![text(&count.to_string())]))
+            }
+        };
+
+        let render = |called: &'static str| {
+            hooks_called.set(called);
+            render_root(context_ptr, root_id, Box::into_raw(Box::new(Element::from_component(component.clone()))));
+
+            flush_scheduled_work(context_ptr).map_err(|error| format!("{:?}", error))
+        };
+
+        assert!(render("state").is_ok());
+
+        let more = render("two states").unwrap_err();
+        assert!(more.starts_with("Reactron: reactron::tests::hooks_called_out_of_order_fail_the_render::{{closure}}"));
+        assert!(more.contains("called more hooks than the 1 of its previous render"));
+
+        let fewer = render("none").unwrap_err();
+        assert!(fewer.contains("rendered 0 hooks, fewer than the 1 of its previous render"));
+
+        let other_kind = render("ref").unwrap_err();
+        assert!(other_kind.contains("called useRef as hook #1, where its previous render called useState"));
+
+        let other_type = render("text state").unwrap_err();
+        assert!(other_type.contains("called use_state with a different type than in its previous render"));
+
+        // Failed renders aren't committed, and don't keep the next ones from rendering
+        assert_eq!(container.inner_html(), "<p>0</p>");
+        assert!(render("state").is_ok());
+    }
+
+    /// Fiber of the root's committed tree at `path`, a child index for every level below the root fiber
+    fn committed_fiber(context_ptr: *mut Context, root_id: u32, path: &[usize]) -> FiberCell {
+        let context = Context::from_ptr(context_ptr);
+        let mut fiber = Rc::clone(context.get_root(root_id).unwrap().borrow().current().unwrap());
+        let _ = Box::into_raw(context);
+
+        for index in path {
+            let mut child = fiber.borrow().child().as_ref().map(Rc::clone).unwrap();
+
+            for _ in 0..*index {
+                let sibling = child.borrow().sibling().as_ref().map(Rc::clone).unwrap();
+                child = sibling;
+            }
+
+            fiber = child;
+        }
+
+        fiber
+    }
+
+    #[test]
+    fn components_keep_their_hooks_across_renders() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let set_count: Rc<RefCell<Option<SetState<u32>>>> = Rc::new(RefCell::new(None));
+
+        let labelled_counter = {
+            let set_count = Rc::clone(&set_count);
+
+            move |hooks: &mut Hooks| {
+                let (count, set) = hooks.use_state(0u32);
+                set_count.replace(Some(set));
+                let (label, _) = hooks.use_state(String::from("count"));
+
+                Some(element("p", None, vec![text(&format!("{} {}", label, count))]))
+            }
+        };
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            Box::new(Element::from_component(labelled_counter)),
+        ]));
+
+        for count in 1..=3 {
+            set_count.borrow().as_ref().unwrap().set(count);
+            flush_scheduled_work(context_ptr).unwrap();
+
+            assert_eq!(container.inner_html(), format!("<div><p>count {}</p></div>", count));
+        }
+
+        // Every render adds its hooks again, rather than after the ones it was reused with
+        assert_eq!(committed_fiber(context_ptr, root_id, &[0, 0]).borrow().hooks().map_or(0, Vec::len), 2);
+    }
+
+    struct RenderCounter {
+        renders: Rc<Cell<u32>>,
+    }
+
+    impl Component for RenderCounter {
+        fn render(&self, _hooks: &mut Hooks) -> Option<Box<Element>> {
+            self.renders.set(self.renders.get() + 1);
+
+            Some(element("span", None, vec![]))
+        }
+    }
+
+    #[test]
+    fn unchanged_siblings_of_an_updated_component_bail_out() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let set_count = Rc::new(RefCell::new(None));
+        let counter_renders = Rc::new(Cell::new(0));
+        let sibling_renders = Rc::new(Cell::new(0));
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            counter(&set_count, &counter_renders),
+            Box::new(Element::from_component(RenderCounter { renders: Rc::clone(&sibling_renders) })),
+        ]));
+
+        set_count.borrow().as_ref().unwrap().set(1);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(container.inner_html(), "<div><p>1</p><span></span></div>");
+        assert_eq!(counter_renders.get(), 2);
+        assert_eq!(sibling_renders.get(), 1);
+    }
+
+    #[test]
+    fn updates_render_from_the_component_that_set_state() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let set_count = Rc::new(RefCell::new(None));
+        let counter_renders = Rc::new(Cell::new(0));
+        let parent_renders = Rc::new(Cell::new(0));
+
+        let parent = {
+            let set_count = Rc::clone(&set_count);
+            let counter_renders = Rc::clone(&counter_renders);
+            let parent_renders = Rc::clone(&parent_renders);
+
+            move |_hooks: &mut Hooks| {
+                parent_renders.set(parent_renders.get() + 1);
+
+                Some(element("section", None, vec![counter(&set_count, &counter_renders)]))
+            }
+        };
+
+        render_and_flush(context_ptr, root_id, Box::new(Element::from_component(parent)));
+
+        // Each update starts from the root, which has to know there's work below it
+        for count in 1..=2 {
+            set_count.borrow().as_ref().unwrap().set(count);
+            flush_scheduled_work(context_ptr).unwrap();
+
+            assert_eq!(container.inner_html(), format!("<section><p>{}</p></section>", count));
+        }
+
+        assert_eq!(counter_renders.get(), 3);
+        assert_eq!(parent_renders.get(), 1);
+    }
+
+    #[test]
+    fn updates_in_one_root_leave_the_others_alone() {
+        let context_ptr = test_context();
+        let (first_id, first) = create_test_root(context_ptr);
+        let (second_id, second) = create_test_root(context_ptr);
+        let set_first = Rc::new(RefCell::new(None));
+        let first_renders = Rc::new(Cell::new(0));
+        let second_renders = Rc::new(Cell::new(0));
+
+        render_and_flush(context_ptr, first_id, counter(&set_first, &first_renders));
+        render_and_flush(context_ptr, second_id, counter(&Rc::new(RefCell::new(None)), &second_renders));
+
+        set_first.borrow().as_ref().unwrap().set(1);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(first.inner_html(), "<p>1</p>");
+        assert_eq!(second.inner_html(), "<p>0</p>");
+        assert_eq!(first_renders.get(), 2);
+        assert_eq!(second_renders.get(), 1);
+    }
+
+    #[test]
+    fn setters_kept_after_unmounting_render_nothing() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let set_count = Rc::new(RefCell::new(None));
+        let renders = Rc::new(Cell::new(0));
+
+        render_and_flush(context_ptr, root_id, counter(&set_count, &renders));
+
+        unmount(context_ptr, root_id);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        // There's no fiber or root left to render
+        set_count.borrow().as_ref().unwrap().set(1);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(renders.get(), 1);
+        assert_eq!(container.inner_html(), "");
+    }
+
+    #[test]
+    fn builds_elements_with_html_macro() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let todos = ["write", "test"];
+        let placeholder = "What needs to be done?";
+
+        render_and_flush(context_ptr, root_id, html! {
+            <div class="app">
+                <Greeting name="macro" />
+                <input class="new-todo" placeholder={placeholder} checked />
+                <ul>
+                    { for todos.iter().map(|todo| html! { <li>{ *todo }</li> }) }
+                </ul>
+                { todos.len() }
+                " todos"
+            </div>
+        });
+
+        assert_eq!(
+            container.inner_html(),
+            "<div class=\"app\"><h1>Hello, macro</h1><input class=\"new-todo\" placeholder=\"What needs to be done?\" checked>\
+            </input><ul><li>write</li><li>test</li></ul>2 todos</div>"
+        );
+    }
+
+    #[test]
+    fn builds_elements_and_matches_siblings_by_key() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+
+        let list = |keys: &[&str]| Element::tag("ul")
+            .children(keys.iter().map(|key| Element::tag("li").key(*key).child(*key)))
+            .build();
+
+        render_and_flush(context_ptr, root_id, Element::tag("div")
+            .class("app")
+            .child(Element::tag("input").attr("type", "checkbox").checked(true))
+            .child(list(&["a", "b"]))
+            .build());
+
+        let first_item = container.children()[0].children()[1].children()[1].clone();
+
+        render_and_flush(context_ptr, root_id, Element::tag("div")
+            .class("app")
+            .child(Element::tag("input").attr("type", "checkbox").checked(true))
+            .child(list(&["c", "b"]))
+            .build());
+
+        let items = container.children()[0].children()[1].children();
+
+        assert_eq!(
+            container.inner_html(),
+            "<div class=\"app\"><input type=\"checkbox\" checked></input><ul><li>c</li><li>b</li></ul></div>"
+        );
+        // The same key at the same position keeps its instance
+        assert!(items[1].is_same(&first_item));
+    }
+
+    #[test]
+    fn builder_ignores_unsupported_attributes() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let warnings = collect_warnings();
+
+        render_and_flush(context_ptr, root_id, Element::tag("input")
+            .attr("type", "text")
+            .attr("autofocus", "")
+            .build());
+
+        assert_eq!(container.inner_html(), "<input type=\"text\"></input>");
+        assert_eq!(*warnings.borrow(), vec!["Reactron: <input> doesn't support the 'autofocus' attribute, so it is ignored"]);
+    }
+
+    #[test]
+    fn reordered_keyed_children_keep_their_instances_and_state() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let setters: [_; 3] = std::array::from_fn(|_| Rc::new(RefCell::new(None)));
+        let renders = Rc::new(Cell::new(0));
+
+        let app = |keys: &[usize]| element("div", None, vec![
+            Element::tag("ul")
+                .children(keys.iter().map(|key| Element::tag("li").key(key.to_string()).child(key.to_string())))
+                .build(),
+            element("section", None, keys.iter().map(|key| {
+                let mut item = counter(&setters[*key], &renders);
+                item.set_key(Some(key.to_string()));
+
+                item
+            }).collect()),
+        ]);
+
+        render_and_flush(context_ptr, root_id, app(&[0, 1, 2]));
+
+        let items = container.children()[0].children()[0].children();
+        setters[0].borrow().as_ref().unwrap().set(10);
+        setters[2].borrow().as_ref().unwrap().set(12);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        render_and_flush(context_ptr, root_id, app(&[2, 0, 1]));
+
+        assert_eq!(
+            container.inner_html(),
+            "<div><ul><li>2</li><li>0</li><li>1</li></ul><section><p>12</p><p>10</p><p>0</p></section></div>"
+        );
+
+        let moved_items = container.children()[0].children()[0].children();
+        assert!(moved_items[0].is_same(&items[2]));
+        assert!(moved_items[1].is_same(&items[0]));
+        assert!(moved_items[2].is_same(&items[1]));
+
+        render_and_flush(context_ptr, root_id, app(&[1, 2]));
+
+        assert_eq!(
+            container.inner_html(),
+            "<div><ul><li>1</li><li>2</li></ul><section><p>0</p><p>12</p></section></div>"
+        );
+        assert!(container.children()[0].children()[0].children()[0].is_same(&items[1]));
+    }
+
+    #[test]
+    fn fragments_render_children_into_the_parent_instance() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+
+        let list = |items: &[&str]| element("ul", None, vec![
+            Box::new(Element::fragment(items.iter().map(|item| element("li", Some(item), vec![])).collect())),
+            element("p", None, vec![]),
+        ]);
+
+        render_and_flush(context_ptr, root_id, list(&["a", "b"]));
+        render_and_flush(context_ptr, root_id, list(&["a", "b", "c"]));
+
+        assert_eq!(
+            container.inner_html(),
+            "<ul><li class=\"a\"></li><li class=\"b\"></li><li class=\"c\"></li><p></p></ul>"
+        );
+
+        render_and_flush(context_ptr, root_id, list(&["a"]));
+
+        assert_eq!(container.inner_html(), "<ul><li class=\"a\"></li><p></p></ul>");
+    }
+
+    #[test]
+    fn strict_mode_renders_components_twice_in_debug_builds() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let set_count: Rc<RefCell<Option<SetState<u32>>>> = Rc::new(RefCell::new(None));
+        let renders = Rc::new(Cell::new(0));
+        let renders_per_update = if cfg!(debug_assertions) { 2 } else { 1 };
+
+        let counter = {
+            let set_count = Rc::clone(&set_count);
+            let renders = Rc::clone(&renders);
+
+            move |hooks: &mut Hooks| {
+                let (count, set) = hooks.use_state(0u32);
+                set_count.replace(Some(set));
+                renders.set(renders.get() + 1);
+
+                Some(element("p", None, vec![text(&count.to_string())]))
+            }
+        };
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            Box::new(Element::from_component(Greeting { name: String::from("strict") })),
+            Box::new(Element::strict_mode(vec![Box::new(Element::from_component(counter))])),
+        ]));
+
+        assert_eq!(container.inner_html(), "<div><h1>Hello, strict</h1><p>0</p></div>");
+        assert_eq!(renders.get(), renders_per_update);
+
+        // The state set from the kept render is the one updated
+        set_count.borrow().as_ref().unwrap().set(1);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(container.inner_html(), "<div><h1>Hello, strict</h1><p>1</p></div>");
+        assert_eq!(renders.get(), 2 * renders_per_update);
+    }
+
+    struct TodoList {
+        keys: Vec<Option<&'static str>>,
+    }
+
+    impl Component for TodoList {
+        fn render(&self, _hooks: &mut Hooks) -> Option<Box<Element>> {
+            Some(Element::tag("ul")
+                .children(self.keys.iter().map(|key| match key {
+                    Some(key) => Element::tag("li").key(*key),
+                    None => Element::tag("li"),
+                }))
+                .build())
+        }
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn warns_about_missing_and_duplicate_keys_in_lists() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let warnings = |context_ptr: *mut Context| {
+            let context = Context::from_ptr(context_ptr);
+            let mut warnings = context.warnings.iter().cloned().collect::<Vec<String>>();
+            let _ = Box::into_raw(context);
+
+            warnings.sort();
+            warnings
+        };
+
+        // Children written out one by one don't need keys
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            Box::new(Element::from_component(TodoList { keys: vec![Some("a"), Some("b")] })),
+            element("p", None, vec![]),
+        ]));
+
+        assert!(warnings(context_ptr).is_empty());
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            Box::new(Element::from_component(TodoList { keys: vec![Some("a"), None, Some("a")] })),
+            element("p", None, vec![]),
+        ]));
+
+        let warnings = warnings(context_ptr);
+
+        assert_eq!(container.inner_html(), "<div><ul><li></li><li></li><li></li></ul><p></p></div>");
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].starts_with("children of <ul> rendered by reactron::tests::TodoList share the key \"a\""));
+        assert!(warnings[1].starts_with("each child in a list should have a unique key"));
+        assert!(warnings[1].ends_with("Check the children of <ul> rendered by reactron::tests::TodoList."));
+    }
+
+    #[test]
+    fn suspense_boundaries_render_children_that_dont_suspend() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let set_count: Rc<RefCell<Option<SetState<u32>>>> = Rc::new(RefCell::new(None));
+
+        let counter = {
+            let set_count = Rc::clone(&set_count);
+
+            move |hooks: &mut Hooks| {
+                let (count, set) = hooks.use_state(0u32);
+                set_count.replace(Some(set));
+
+                Some(element("p", None, vec![text(&count.to_string())]))
+            }
+        };
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            Box::new(Element::suspense(
+                vec![element("span", None, vec![text("Loading")])],
+                vec![Box::new(Element::from_component(counter))],
+            )),
+        ]));
+
+        assert_eq!(container.inner_html(), "<div><p>0</p></div>");
+
+        // Updates below the boundary reach it like any other fiber
+        set_count.borrow().as_ref().unwrap().set(1);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(container.inner_html(), "<div><p>1</p></div>");
+    }
+
+    type WakeCallbacks = Rc<RefCell<Vec<Box<dyn FnOnce()>>>>;
+
+    /// Data Rust components suspend on until it's resolved, like a promise JS components throw
+    #[derive(Clone, Default)]
+    struct TestResource {
+        value: Rc<RefCell<Option<String>>>,
+        waiting: WakeCallbacks,
+    }
+
+    impl TestResource {
+        fn resolve(&self, value: &str) {
+            self.value.replace(Some(String::from(value)));
+
+            for callback in self.waiting.take() {
+                callback();
+            }
+        }
+
+        fn is_waited_for(&self) -> bool {
+            !self.waiting.borrow().is_empty()
+        }
+    }
+
+    impl Wakeable for TestResource {
+        fn when_settled(&self, callback: Box<dyn FnOnce()>) {
+            self.waiting.borrow_mut().push(callback);
+        }
+    }
+
+    /// Component rendering the value of `resource` in a paragraph, suspending until it has one
+    fn resource_reader(resource: &TestResource) -> Box<Element> {
+        let resource = resource.clone();
+
+        Box::new(Element::from_component(move |hooks: &mut Hooks| {
+            let value = resource.value.borrow().clone();
+
+            match value {
+                Some(value) => Some(element("p", None, vec![text(&value)])),
+                None => {
+                    hooks.suspend(resource.clone());
+                    None
+                },
+            }
+        }))
+    }
+
+    fn loading(label: &str) -> Box<Element> {
+        element("span", None, vec![text(label)])
+    }
+
+    #[test]
+    fn suspended_components_show_the_fallback_until_they_can_render() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let resource = TestResource::default();
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            Box::new(Element::suspense(vec![loading("Loading")], vec![
+                element("h1", None, vec![text("Title")]),
+                resource_reader(&resource),
+            ])),
+        ]));
+
+        // Siblings rendered before the component suspended are discarded with it
+        assert_eq!(container.inner_html(), "<div><span>Loading</span></div>");
+
+        resource.resolve("ready");
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(container.inner_html(), "<div><h1>Title</h1><p>ready</p></div>");
+        assert!(!committed_fiber(context_ptr, root_id, &[0, 0]).borrow().is_suspended());
+    }
+
+    #[test]
+    fn suspended_components_are_caught_by_the_closest_boundary() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let (outer, inner, fallback) = (TestResource::default(), TestResource::default(), TestResource::default());
+
+        render_and_flush(context_ptr, root_id, element("div", None, vec![
+            Box::new(Element::suspense(vec![loading("Outer")], vec![
+                resource_reader(&outer),
+                Box::new(Element::suspense(vec![loading("Inner")], vec![resource_reader(&inner)])),
+                // A fallback suspending is caught by the boundary above its own
+                Box::new(Element::suspense(vec![resource_reader(&fallback)], vec![resource_reader(&inner)])),
+            ])),
+        ]));
+
+        assert_eq!(container.inner_html(), "<div><span>Outer</span></div>");
+
+        outer.resolve("outer");
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(container.inner_html(), "<div><span>Outer</span></div>");
+        assert!(fallback.is_waited_for());
+
+        fallback.resolve("fallback");
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(container.inner_html(), "<div><p>outer</p><span>Inner</span><p>fallback</p></div>");
+
+        inner.resolve("inner");
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(container.inner_html(), "<div><p>outer</p><p>inner</p><p>inner</p></div>");
+    }
+
+    #[test]
+    fn suspending_without_a_boundary_fails_the_render() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let resource = TestResource::default();
+
+        render_root(context_ptr, root_id, Box::into_raw(element("div", None, vec![resource_reader(&resource)])));
+        let error = format!("{:?}", flush_scheduled_work(context_ptr).unwrap_err());
+
+        assert!(error.contains("suspended while rendering, but there's no Suspense boundary above it to render a fallback"));
+        assert_eq!(container.inner_html(), "");
+        assert!(!resource.is_waited_for());
+    }
+
+    #[test]
+    fn streams_only_the_fallback_of_suspended_boundaries() {
+        let context_ptr = test_context();
+        let resource = TestResource::default();
+
+        let stream = RenderStream::new(context_ptr, *element("div", None, vec![
+            element("h1", None, vec![text("Title")]),
+            Box::new(Element::suspense(vec![loading("Loading")], vec![
+                element("section", None, vec![
+                    element("p", None, vec![text("before")]),
+                    resource_reader(&resource),
+                ]),
+            ])),
+        ]), 1);
+
+        // What the boundary rendered before its child suspended is never streamed
+        let chunks = stream.collect::<Result<Vec<String>, Error>>().unwrap();
+        assert_eq!(chunks, vec!["<div><h1>Title</h1>", "<span>Loading</span></div>"]);
+
+        // Nothing renders the boundary again once the stream ended
+        assert!(!resource.is_waited_for());
+    }
+
+    #[test]
+    fn abandoned_renders_dont_leave_the_boundary_suspended() {
+        let context_ptr = test_context();
+        let (root_id, container) = create_test_root(context_ptr);
+        let resource = TestResource::default();
+        resource.value.replace(Some(String::from("ready")));
+
+        let tree = |resource: &TestResource| Box::into_raw(Box::new(Element::suspense(vec![loading("Loading")], vec![
+            resource_reader(resource),
+        ])));
+
+        render_root(context_ptr, root_id, tree(&resource));
+        flush_scheduled_work(context_ptr).unwrap();
+        assert_eq!(container.inner_html(), "<p>ready</p>");
+
+        // A render that suspends, interrupted before it commits
+        let pending = TestResource::default();
+        render_root(context_ptr, root_id, tree(&pending));
+
+        let mut context = Context::from_ptr(context_ptr);
+
+        while !pending.is_waited_for() {
+            assert_eq!(context.work_loop(&YieldAfter(Cell::new(2))).ok(), Some(true));
+        }
+
+        let _ = Box::into_raw(context);
+
+        assert_eq!(container.inner_html(), "<p>ready</p>");
+        assert!(!committed_fiber(context_ptr, root_id, &[0]).borrow().is_suspended());
+
+        // The render replacing it doesn't suspend, so it shows the children
+        set_update_lane(context_ptr, Lane::Sync);
+        render_root(context_ptr, root_id, tree(&resource));
+        set_update_lane(context_ptr, Lane::Default);
+        flush_scheduled_work(context_ptr).unwrap();
+
+        assert_eq!(container.inner_html(), "<p>ready</p>");
+    }
+
+    // JS components and idle deadlines need a JS engine
+    #[cfg(target_arch = "wasm32")]
+    mod js {
+        use super::*;
+        use js_sys::{Array, Function, Object, Reflect};
+        use web_sys::IdleDeadline;
+        use wasm_bindgen_test::wasm_bindgen_test;
+        use element::{create_class_component, create_functional_component, memo};
+
+        /// Idle deadline with time left for a number of checks, or a timed out one with no time left
+        fn deadline(checks: u32, did_timeout: bool) -> IdleDeadline {
+            let remaining = Cell::new(checks);
+            let time_remaining = Closure::wrap(Box::new(move || {
+                let checks = remaining.get();
+                remaining.set(checks.saturating_sub(1));
+
+                if checks > 0 { 10.0 } else { 0.0 }
+            }) as Box<dyn FnMut() -> f64>);
+
+            let deadline = Object::new();
+            let _ = Reflect::set(&deadline, &JsValue::from_str("timeRemaining"), &time_remaining.into_js_value());
+            let _ = Reflect::set(&deadline, &JsValue::from_str("didTimeout"), &JsValue::from_bool(did_timeout));
+
+            deadline.unchecked_into()
+        }
+
+        #[wasm_bindgen_test]
+        fn idle_deadlines_yield_once_the_time_runs_out_unless_timed_out() {
+            let idle = deadline(1, false);
+            assert!(!idle.should_yield());
+            assert!(idle.should_yield());
+
+            assert!(!deadline(0, true).should_yield());
+        }
+
+        /// JS function component rendering what `render` returns for its props
+        fn js_component(render: impl Fn(&JsValue) -> Box<Element> + 'static) -> Function {
+            Closure::wrap(Box::new(move |props: JsValue| {
+                JsValue::from(Box::into_raw(render(&props)) as u32)
+            }) as Box<dyn FnMut(JsValue) -> JsValue>)
+                .into_js_value()
+                .unchecked_into()
+        }
+
+        fn component_element(component: &Function, props: JsValue) -> Box<Element> {
+            Element::from_ptr(create_functional_component(component.clone(), props))
+        }
+
+        /// Callback setting a state to `value`
+        fn setting(set_state: &Function, value: u32) -> Function {
+            let set_state = set_state.clone();
+
+            Closure::wrap(Box::new(move || {
+                set_state.call1(&JsValue::null(), &JsValue::from(value)).unwrap();
+            }) as Box<dyn FnMut()>).into_js_value().unchecked_into()
+        }
+
+        fn set(set_state: &Function, value: u32) {
+            setting(set_state, value).call0(&JsValue::null()).unwrap();
+        }
+
+        /// Calls a state setter the way an update made on `lane` does
+        fn set_on_lane(context_ptr: *mut Context, lane: Lane, set_state: &Function, value: u32) {
+            run_with_lane(context_ptr, lane, &setting(set_state, value)).unwrap();
+        }
+
+        /// Component with two pieces of state, keeping their setters and the values it rendered
+        fn pair_component(
+            context_ptr: *mut Context,
+            setters: &Rc<RefCell<Vec<Function>>>,
+            rendered: &Rc<RefCell<Vec<(u32, u32)>>>
+        ) -> Function {
+            let setters = Rc::clone(setters);
+            let rendered = Rc::clone(rendered);
+
+            js_component(move |_props| {
+                let mut setters = setters.borrow_mut();
+                setters.clear();
+
+                let values = [0, 1].map(|_| {
+                    let state = use_state(context_ptr, JsValue::from(0));
+                    setters.push(state[1].clone().unchecked_into());
+
+                    state[0].as_f64().unwrap() as u32
+                });
+                rendered.borrow_mut().push((values[0], values[1]));
+
+                element("p", None, vec![text(&format!("{} {}", values[0], values[1]))])
+            })
+        }
+
+        /// Renders a pair component into a new root
+        fn render_pair(
+            context_ptr: *mut Context,
+            setters: &Rc<RefCell<Vec<Function>>>,
+            rendered: &Rc<RefCell<Vec<(u32, u32)>>>
+        ) -> (u32, TestInstance) {
+            let (root_id, container) = create_test_root(context_ptr);
+            let pair = pair_component(context_ptr, setters, rendered);
+
+            render_and_flush(context_ptr, root_id, component_element(&pair, JsValue::null()));
+
+            (root_id, container)
+        }
+
+        #[wasm_bindgen_test]
+        fn less_urgent_updates_are_left_for_a_later_render() {
+            let context_ptr = test_context();
+            let setters = Rc::new(RefCell::new(Vec::new()));
+            let rendered = Rc::new(RefCell::new(Vec::new()));
+            let (_, container) = render_pair(context_ptr, &setters, &rendered);
+
+            let (set_first, set_second) = (setters.borrow()[0].clone(), setters.borrow()[1].clone());
+            set_on_lane(context_ptr, Lane::Transition, &set_first, 1);
+            set_on_lane(context_ptr, Lane::Default, &set_second, 1);
+
+            flush_scheduled_work(context_ptr).unwrap();
+
+            // The default update is rendered first, without the transition made before it
+            assert_eq!(*rendered.borrow(), vec![(0, 0), (0, 1), (1, 1)]);
+            assert_eq!(container.inner_html(), "<p>1 1</p>");
+        }
+
+        #[wasm_bindgen_test]
+        fn flush_sync_commits_its_updates_before_returning() {
+            let context_ptr = test_context();
+            let setters = Rc::new(RefCell::new(Vec::new()));
+            let rendered = Rc::new(RefCell::new(Vec::new()));
+            let (_, container) = render_pair(context_ptr, &setters, &rendered);
+
+            let (set_first, set_second) = (setters.borrow()[0].clone(), setters.borrow()[1].clone());
+            set_on_lane(context_ptr, Lane::Transition, &set_first, 1);
+            flush_sync(context_ptr, &setting(&set_second, 1)).unwrap();
+
+            // Other lanes are left to the scheduler
+            assert_eq!(container.inner_html(), "<p>0 1</p>");
+
+            flush_scheduled_work(context_ptr).unwrap();
+
+            assert_eq!(*rendered.borrow(), vec![(0, 0), (0, 1), (1, 1)]);
+            assert_eq!(container.inner_html(), "<p>1 1</p>");
+        }
+
+        #[wasm_bindgen_test]
+        fn updates_made_together_are_rendered_once() {
+            let scheduler = Rc::new(CountingScheduler::default());
+            let context_ptr = Box::into_raw(Box::new(Context::new(Rc::clone(&scheduler) as Rc<dyn Scheduler>)));
+            let setters = Rc::new(RefCell::new(Vec::new()));
+            let rendered = Rc::new(RefCell::new(Vec::new()));
+            let (_, container) = render_pair(context_ptr, &setters, &rendered);
+
+            // As an event handler would
+            let (set_first, set_second) = (setters.borrow()[0].clone(), setters.borrow()[1].clone());
+            set(&set_first, 1);
+            set(&set_second, 1);
+            set(&set_second, 2);
+
+            assert_eq!(scheduler.scheduled.get(), 2);
+
+            flush_scheduled_work(context_ptr).unwrap();
+
+            assert_eq!(*rendered.borrow(), vec![(0, 0), (1, 2)]);
+            assert_eq!(container.inner_html(), "<p>1 2</p>");
+        }
+
+        #[wasm_bindgen_test]
+        fn setting_the_current_state_schedules_no_render() {
+            let scheduler = Rc::new(CountingScheduler::default());
+            let context_ptr = Box::into_raw(Box::new(Context::new(Rc::clone(&scheduler) as Rc<dyn Scheduler>)));
+            let setters = Rc::new(RefCell::new(Vec::new()));
+            let rendered = Rc::new(RefCell::new(Vec::new()));
+            render_pair(context_ptr, &setters, &rendered);
+
+            let set_first = setters.borrow()[0].clone();
+            set(&set_first, 0);
+            flush_scheduled_work(context_ptr).unwrap();
+
+            assert_eq!(scheduler.scheduled.get(), 1);
+            assert_eq!(rendered.borrow().len(), 1);
+        }
+
+        /// Component rendering an empty span, counting its renders
+        fn span_component(renders: &Rc<Cell<u32>>) -> Function {
+            let renders = Rc::clone(renders);
+
+            js_component(move |_props| {
+                renders.set(renders.get() + 1);
+
+                element("span", None, vec![])
+            })
+        }
+
+        #[wasm_bindgen_test]
+        fn unchanged_siblings_of_an_updated_component_bail_out() {
+            let context_ptr = test_context();
+            let (root_id, container) = create_test_root(context_ptr);
+            let setters = Rc::new(RefCell::new(Vec::new()));
+            let rendered = Rc::new(RefCell::new(Vec::new()));
+            let sibling_renders = Rc::new(Cell::new(0));
+            let pair = pair_component(context_ptr, &setters, &rendered);
+
+            render_and_flush(context_ptr, root_id, element("div", None, vec![
+                component_element(&pair, JsValue::null()),
+                component_element(&span_component(&sibling_renders), JsValue::null()),
+            ]));
+
+            for count in 1..=3 {
+                let set_first = setters.borrow()[0].clone();
+                set(&set_first, count);
+                flush_scheduled_work(context_ptr).unwrap();
+
+                assert_eq!(container.inner_html(), format!("<div><p>{} 0</p><span></span></div>", count));
+            }
+
+            assert_eq!(rendered.borrow().len(), 4);
+            assert_eq!(sibling_renders.get(), 1);
+
+            // Every render adds its hooks again, rather than after the ones it was reused with
+            assert_eq!(committed_fiber(context_ptr, root_id, &[0, 0]).borrow().hooks().map_or(0, Vec::len), 2);
+        }
+
+        #[wasm_bindgen_test]
+        fn updates_render_from_the_component_that_set_state() {
+            let context_ptr = test_context();
+            let (root_id, container) = create_test_root(context_ptr);
+            let setters = Rc::new(RefCell::new(Vec::new()));
+            let rendered = Rc::new(RefCell::new(Vec::new()));
+            let parent_renders = Rc::new(Cell::new(0));
+
+            let parent = {
+                let pair = pair_component(context_ptr, &setters, &rendered);
+                let parent_renders = Rc::clone(&parent_renders);
+
+                js_component(move |_props| {
+                    parent_renders.set(parent_renders.get() + 1);
+
+                    element("section", None, vec![component_element(&pair, JsValue::null())])
+                })
+            };
+
+            render_and_flush(context_ptr, root_id, component_element(&parent, JsValue::null()));
+
+            // Each update starts from the root, which has to know there's work below it
+            for count in 1..=2 {
+                let set_second = setters.borrow()[1].clone();
+                set(&set_second, count);
+                flush_scheduled_work(context_ptr).unwrap();
+
+                assert_eq!(container.inner_html(), format!("<section><p>0 {}</p></section>", count));
+            }
+
+            assert_eq!(rendered.borrow().len(), 3);
+            assert_eq!(parent_renders.get(), 1);
+        }
+
+        #[wasm_bindgen_test]
+        fn hooks_keep_their_state_per_component_and_call_order() {
+            let context_ptr = test_context();
+            let (root_id, container) = create_test_root(context_ptr);
+            let labelled_setters = Rc::new(RefCell::new(Vec::new()));
+            let setters = Rc::new(RefCell::new(Vec::new()));
+            let rendered = Rc::new(RefCell::new(Vec::new()));
+
+            let labelled = {
+                let labelled_setters = Rc::clone(&labelled_setters);
+
+                js_component(move |_props| {
+                    let label = use_state(context_ptr, JsValue::from_str("count"));
+                    let count = use_state(context_ptr, JsValue::from(0));
+                    labelled_setters.replace(vec![label[1].clone(), count[1].clone()]);
+
+                    let label = label[0].as_string().unwrap();
+                    let count = count[0].as_f64().unwrap();
+
+                    element("p", None, vec![text(&format!("{}: {}", label, count))])
+                })
+            };
+            let pair = pair_component(context_ptr, &setters, &rendered);
+
+            render_and_flush(context_ptr, root_id, element("div", None, vec![
+                component_element(&labelled, JsValue::null()),
+                component_element(&pair, JsValue::null()),
+            ]));
+
+            assert_eq!(container.inner_html(), "<div><p>count: 0</p><p>0 0</p></div>");
+
+            let (set_label, set_count): (Function, Function) = {
+                let labelled_setters = labelled_setters.borrow();
+                (labelled_setters[0].clone().unchecked_into(), labelled_setters[1].clone().unchecked_into())
+            };
+            set(&set_count, 2);
+            set_label.call1(&JsValue::null(), &JsValue::from_str("total")).unwrap();
+            flush_scheduled_work(context_ptr).unwrap();
+
+            // Each hook keeps its own state, and other components' state isn't touched
+            assert_eq!(container.inner_html(), "<div><p>total: 2</p><p>0 0</p></div>");
+            assert_eq!(rendered.borrow().len(), 1);
+
+            let set_first = setters.borrow()[0].clone();
+            set(&set_first, 5);
+            flush_scheduled_work(context_ptr).unwrap();
+
+            assert_eq!(container.inner_html(), "<div><p>total: 2</p><p>5 0</p></div>");
+        }
+
+        #[wasm_bindgen_test]
+        fn setters_kept_after_unmounting_render_nothing() {
+            let context_ptr = test_context();
+            let setters = Rc::new(RefCell::new(Vec::new()));
+            let rendered = Rc::new(RefCell::new(Vec::new()));
+            let (root_id, container) = render_pair(context_ptr, &setters, &rendered);
+
+            unmount(context_ptr, root_id);
+            flush_scheduled_work(context_ptr).unwrap();
+
+            // There's no fiber or root left to render
+            let set_first = setters.borrow()[0].clone();
+            set(&set_first, 1);
+            flush_scheduled_work(context_ptr).unwrap();
+
+            assert_eq!(rendered.borrow().len(), 1);
+            assert_eq!(container.inner_html(), "");
+        }
+
+        fn label_props(label: &str) -> JsValue {
+            let props = Object::new();
+            let _ = Reflect::set(&props, &JsValue::from_str("label"), &JsValue::from_str(label));
+
+            props.into()
+        }
+
+        fn label(props: &JsValue) -> String {
+            Reflect::get(props, &JsValue::from_str("label"))
+                .ok()
+                .and_then(|label| label.as_string())
+                .unwrap_or_default()
+        }
+
+        /// Component rendering its label in a paragraph, counting its renders
+        fn label_component(renders: &Rc<Cell<u32>>) -> Function {
+            let renders = Rc::clone(renders);
+
+            js_component(move |props| {
+                renders.set(renders.get() + 1);
+
+                element("p", None, vec![text(&label(props))])
+            })
+        }
+
+        #[wasm_bindgen_test]
+        fn memoized_components_skip_rendering_while_props_are_shallowly_equal() {
+            let context_ptr = test_context();
+            let (root_id, container) = create_test_root(context_ptr);
+            let renders = Rc::new(Cell::new(0));
+            let component = label_component(&renders);
+
+            let tree = |label: &str| element("div", None, vec![
+                Element::from_ptr(memo(create_functional_component(component.clone(), label_props(label)), None)),
+            ]);
+
+            render_and_flush(context_ptr, root_id, tree("a"));
+
+            // The parent renders a new props object, with the same values
+            render_and_flush(context_ptr, root_id, tree("a"));
+            assert_eq!(renders.get(), 1);
+
+            render_and_flush(context_ptr, root_id, tree("b"));
+            assert_eq!(renders.get(), 2);
+            assert_eq!(container.inner_html(), "<div><p>b</p></div>");
+        }
+
+        #[wasm_bindgen_test]
+        fn memoized_components_compare_props_with_a_custom_function() {
+            let context_ptr = test_context();
+            let (root_id, container) = create_test_root(context_ptr);
+            let renders = Rc::new(Cell::new(0));
+            let component = label_component(&renders);
+            let same_length = Function::new_with_args("prev, next", "return prev.label.length === next.label.length");
+
+            let tree = |label: &str| element("div", None, vec![
+                Element::from_ptr(memo(
+                    create_functional_component(component.clone(), label_props(label)),
+                    Some(same_length.clone())
+                )),
+            ]);
+
+            render_and_flush(context_ptr, root_id, tree("a"));
+            render_and_flush(context_ptr, root_id, tree("b"));
+
+            assert_eq!(renders.get(), 1);
+            assert_eq!(container.inner_html(), "<div><p>a</p></div>");
+
+            render_and_flush(context_ptr, root_id, tree("bb"));
+
+            assert_eq!(renders.get(), 2);
+            assert_eq!(container.inner_html(), "<div><p>bb</p></div>");
+        }
+
+        #[wasm_bindgen_test]
+        fn components_without_memo_render_with_their_parent() {
+            let context_ptr = test_context();
+            let (root_id, _container) = create_test_root(context_ptr);
+            let renders = Rc::new(Cell::new(0));
+            let component = label_component(&renders);
+
+            for _ in 0..2 {
+                render_and_flush(context_ptr, root_id, element("div", None, vec![
+                    component_element(&component, label_props("a")),
+                ]));
+            }
+
+            assert_eq!(renders.get(), 2);
+        }
+
+        /// JS class component rendering what `render` returns for its state, which starts with a
+        /// `count` and the `label` prop. It logs its renders and lifecycle methods as `name`, and
+        /// its instances are collected in `instances`.
+        fn js_class(
+            name: &str,
+            log: &Array,
+            instances: &Array,
+            render: impl Fn(&JsValue) -> Box<Element> + 'static
+        ) -> Function {
+            let class = Function::new_with_args("name, log, instances, render", r#"
+                return class {
+                    constructor(props) {
+                        this.props = props;
+                        this.state = { count: 0, label: props.label };
+                        instances.push(this);
+                    }
+
+                    setState(partialState) {
+                        this.__reactronUpdater(partialState);
+                    }
+
+                    componentDidMount() {
+                        log.push(`${name} mount`);
+                    }
+
+                    componentDidUpdate(prevProps, prevState) {
+                        log.push(`${name} update from ${prevState.count}`);
+                    }
+
+                    componentWillUnmount() {
+                        log.push(`${name} unmount`);
+                    }
+
+                    render() {
+                        log.push(`${name} render`);
+                        return render(this.state);
+                    }
+                };
+            "#);
+
+            let args = Array::of4(&JsValue::from_str(name), log, instances, &js_component(render));
+            class.apply(&JsValue::null(), &args).unwrap().unchecked_into()
+        }
+
+        fn take_log(log: &Array) -> Vec<String> {
+            let entries = log.iter().map(|entry| entry.as_string().unwrap()).collect();
+            log.set_length(0);
+
+            entries
+        }
+
+        fn class_element(class: &Function, label: &str) -> Box<Element> {
+            Element::from_ptr(create_class_component(class.clone(), label_props(label)))
+        }
+
+        #[wasm_bindgen_test]
+        fn class_components_run_lifecycle_methods_children_first() {
+            let context_ptr = test_context();
+            let (root_id, _container) = create_test_root(context_ptr);
+            let log = Array::new();
+            let instances = Array::new();
+
+            let child = js_class("child", &log, &instances, |state| element("p", None, vec![text(&label(state))]));
+            let parent = js_class("parent", &log, &instances, move |state| {
+                element("div", None, vec![class_element(&child, &label(state))])
+            });
+
+            render_and_flush(context_ptr, root_id, element("main", None, vec![class_element(&parent, "a")]));
+            assert_eq!(take_log(&log), ["parent render", "child render", "child mount", "parent mount"]);
+
+            render_and_flush(context_ptr, root_id, element("main", None, vec![class_element(&parent, "b")]));
+            assert_eq!(take_log(&log), ["parent render", "child render", "child update from 0", "parent update from 0"]);
+
+            render_and_flush(context_ptr, root_id, element("main", None, vec![]));
+            assert_eq!(take_log(&log), ["parent unmount", "child unmount"]);
+
+            // Instances are created once, when they're mounted
+            assert_eq!(instances.length(), 2);
+        }
+
+        #[wasm_bindgen_test]
+        fn set_state_merges_partial_states_and_calls_updater_functions() {
+            let context_ptr = test_context();
+            let (root_id, container) = create_test_root(context_ptr);
+            let log = Array::new();
+            let instances = Array::new();
+
+            let counter = js_class("counter", &log, &instances, |state| {
+                let count = Reflect::get(state, &JsValue::from_str("count")).unwrap().as_f64().unwrap();
+                element("p", None, vec![text(&format!("{} {}", label(state), count))])
+            });
+
+            render_and_flush(context_ptr, root_id, class_element(&counter, "clicks"));
+
+            let set_state = |script: &str| {
+                Function::new_with_args("instance", script).call1(&JsValue::null(), &instances.get(0)).unwrap();
+                flush_scheduled_work(context_ptr).unwrap();
+            };
+
+            // Partial states are merged into the state, and the ones set together are rendered once
+            set_state("instance.setState({ count: 1 }); instance.setState({ count: 2 });");
+            assert_eq!(container.inner_html(), "<p>clicks 2</p>");
+
+            // Updater functions get the latest state, with the ones before it applied, and the props
+            set_state("instance.setState((state) => ({ count: state.count + 1 })); \
+                instance.setState((state, props) => ({ count: state.count + props.label.length }));");
+            assert_eq!(container.inner_html(), "<p>clicks 9</p>");
+
+            assert_eq!(take_log(&log), [
+                "counter render", "counter mount",
+                "counter render", "counter update from 0",
+                "counter render", "counter update from 2",
+            ]);
+        }
+
+        #[wasm_bindgen_test]
+        fn throwing_lifecycle_methods_leave_the_commit_finished() {
+            let context_ptr = test_context();
+            let (root_id, container) = create_test_root(context_ptr);
+            let log = Array::new();
+            let instances = Array::new();
+
+            let failing = js_class("failing", &log, &instances, |state| element("p", None, vec![text(&label(state))]));
+            let _ = Function::new_with_args("class", "class.prototype.componentDidMount = () => { throw new Error('mount'); };")
+                .call1(&JsValue::null(), &failing);
+            let other = js_class("other", &log, &instances, |state| element("p", None, vec![text(&label(state))]));
+
+            render_root(context_ptr, root_id, Box::into_raw(element("div", None, vec![
+                class_element(&failing, "a"),
+                class_element(&other, "b"),
+            ])));
+
+            let mut context = Context::from_ptr(context_ptr);
+            let result = context.work_loop(&Unbounded);
+            let _ = Box::into_raw(context);
+
+            // The error is reported once the tree is committed and the other lifecycle methods ran
+            assert!(result.is_err());
+            assert_eq!(container.inner_html(), "<div><p>a</p><p>b</p></div>");
+            assert_eq!(take_log(&log), ["failing render", "other render", "other mount"]);
+
+            render_and_flush(context_ptr, root_id, element("div", None, vec![class_element(&other, "c")]));
+
+            assert_eq!(container.inner_html(), "<div><p>c</p></div>");
+            assert_eq!(take_log(&log), ["other render", "failing unmount", "other unmount", "other mount"]);
+        }
+
+        #[wasm_bindgen_test]
+        fn strict_mode_renders_components_given_children_once() {
+            let context_ptr = test_context();
+            let (root_id, container) = create_test_root(context_ptr);
+            let renders = Rc::new(Cell::new(0));
+
+            // Renders the element handles in `props.children` into a section
+            let wrapper = {
+                let renders = Rc::clone(&renders);
+
+                js_component(move |props| {
+                    renders.set(renders.get() + 1);
+
+                    let children = Reflect::get(props, &JsValue::from_str("children")).unwrap();
+                    let children = Array::from(&children).iter()
+                        .map(|child| Element::from_ptr(child.as_f64().unwrap() as u32 as *mut Element))
+                        .collect();
+
+                    element("section", None, children)
+                })
+            };
+
+            let props = Object::new();
+            let children = Array::of1(&JsValue::from(Box::into_raw(element("p", None, vec![text("child")])) as u32));
+            let _ = Reflect::set(&props, &JsValue::from_str("children"), &children);
+
+            render_and_flush(context_ptr, root_id, Box::new(Element::strict_mode(vec![
+                Element::from_ptr(create_functional_component(wrapper, props.into())),
+            ])));
+
+            assert_eq!(container.inner_html(), "<section><p>child</p></section>");
+            assert_eq!(renders.get(), 1);
+        }
+
+        /// Element of a JS function component running `body`
+        fn throwing_element(body: &str) -> Box<Element> {
+            Element::from_ptr(create_functional_component(Function::new_with_args("props", body), JsValue::null()))
+        }
+
+        #[wasm_bindgen_test]
+        fn thrown_promises_show_the_closest_fallback() {
+            let context_ptr = test_context();
+            let (root_id, container) = create_test_root(context_ptr);
+
+            render_and_flush(context_ptr, root_id, element("div", None, vec![
+                Box::new(Element::suspense(vec![element("span", None, vec![text("Loading")])], vec![
+                    element("h1", None, vec![text("Title")]),
+                    throwing_element("throw new Promise(() => {});"),
+                ])),
+            ]));
+
+            assert_eq!(container.inner_html(), "<div><span>Loading</span></div>");
+        }
+
+        #[wasm_bindgen_test]
+        fn thrown_errors_fail_the_render() {
+            let context_ptr = test_context();
+            let (root_id, container) = create_test_root(context_ptr);
+
+            render_and_flush(context_ptr, root_id, element("p", None, vec![text("before")]));
+            render_root(context_ptr, root_id, Box::into_raw(element("div", None, vec![
+                throwing_element("throw new Error('broken');"),
+            ])));
+
+            let error = JsValue::from(flush_scheduled_work(context_ptr).unwrap_err());
+
+            assert_eq!(Reflect::get(&error, &JsValue::from_str("message")).unwrap(), "broken");
+            assert_eq!(container.inner_html(), "<p>before</p>");
+        }
+    }
 }
@@ -0,0 +1,267 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use super::{ClassInstance, Element, Fiber, FiberCell, HookCell, HostConfig, Hydration, Lane, Lanes, Node};
+
+pub type RootCell = Rc<RefCell<Root>>;
+
+/// How much work the render in progress had done at some point, so it can be rewound to it
+#[derive(Clone, Copy)]
+pub struct WorkMark {
+    effects: usize,
+    hooks: usize,
+    class_instances: usize,
+}
+
+/// Tree mounted into a container. Roots of the same context are rendered and
+/// committed independently, but share its scheduler.
+pub struct Root {
+    id: u32,
+    container: Rc<RefCell<Node>>,
+    host: Rc<dyn HostConfig>,
+    // Children set since the last render started, which the next one takes over. Renders
+    // without new children render those of the current tree again.
+    children: Option<Vec<Element>>,
+    current: Option<FiberCell>,
+    wip: Option<FiberCell>,
+    next_unit_of_work: Option<FiberCell>,
+    effects: Vec<FiberCell>,
+    is_unmounting: bool,
+    is_hydrating: bool,
+    hydration: Option<Hydration>,
+
+    // Lanes
+    wip_lane: Option<Lane>,
+    pending_lanes: Lanes,
+    wip_hooks: Vec<(HookCell, usize)>,
+    wip_class_instances: Vec<Rc<ClassInstance>>,
+}
+
+impl Root {
+    pub fn new(id: u32, container: Node, host: Rc<dyn HostConfig>) -> Self {
+        Root {
+            id,
+            container: Rc::new(RefCell::new(container)),
+            host,
+            children: None,
+            current: None,
+            wip: None,
+            next_unit_of_work: None,
+            effects: Vec::new(),
+            is_unmounting: false,
+            is_hydrating: false,
+            hydration: None,
+            wip_lane: None,
+            pending_lanes: Lanes::default(),
+            wip_hooks: Vec::new(),
+            wip_class_instances: Vec::new(),
+        }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+
+    pub fn container(&self) -> &Rc<RefCell<Node>> {
+        &self.container
+    }
+
+    pub fn host(&self) -> &Rc<dyn HostConfig> {
+        &self.host
+    }
+
+    pub fn set_children(&mut self, children: Vec<Element>) {
+        self.children = Some(children);
+    }
+
+    #[cfg(test)]
+    pub fn current(&self) -> Option<&FiberCell> {
+        self.current.as_ref()
+    }
+
+    pub fn is_unmounting(&self) -> bool {
+        self.is_unmounting
+    }
+
+    pub fn set_unmounting(&mut self, is_unmounting: bool) {
+        self.is_unmounting = is_unmounting;
+    }
+
+    /// Makes the next render claim the instances already in the container
+    pub fn start_hydration(&mut self) {
+        self.is_hydrating = true;
+    }
+
+    pub fn hydration_mut(&mut self) -> Option<&mut Hydration> {
+        self.hydration.as_mut()
+    }
+
+    pub fn is_mounted(&self) -> bool {
+        self.current.is_some()
+    }
+
+    pub fn take_current(&mut self) -> Option<FiberCell> {
+        self.current.take()
+    }
+
+    pub fn wip(&self) -> Option<&FiberCell> {
+        self.wip.as_ref()
+    }
+
+    pub fn next_unit_of_work(&self) -> Option<&FiberCell> {
+        self.next_unit_of_work.as_ref()
+    }
+
+    pub fn set_next_unit_of_work(&mut self, fiber: Option<FiberCell>) {
+        self.next_unit_of_work = fiber;
+    }
+
+    pub fn wip_lane(&self) -> Option<Lane> {
+        self.wip_lane
+    }
+
+    /// Lane of the work this root has to do next, if any
+    pub fn next_lane(&self) -> Option<Lane> {
+        self.wip_lane.or_else(|| self.pending_lanes.highest_priority())
+    }
+
+    pub fn add_effect(&mut self, effect: FiberCell) {
+        self.effects.push(effect);
+    }
+
+    pub fn take_effects(&mut self) -> Vec<FiberCell> {
+        std::mem::take(&mut self.effects)
+    }
+
+    pub fn add_wip_hook(&mut self, hook: HookCell, rendered_len: usize) {
+        self.wip_hooks.push((hook, rendered_len));
+    }
+
+    pub fn mark_work(&self) -> WorkMark {
+        WorkMark {
+            effects: self.effects.len(),
+            hooks: self.wip_hooks.len(),
+            class_instances: self.wip_class_instances.len(),
+        }
+    }
+
+    /// Forgets the work done since `mark`, whose render was thrown away
+    pub fn rewind_work(&mut self, mark: WorkMark) {
+        self.effects.truncate(mark.effects);
+        self.wip_hooks.truncate(mark.hooks);
+        self.wip_class_instances.truncate(mark.class_instances);
+    }
+
+    pub fn add_wip_class_instance(&mut self, instance: Rc<ClassInstance>) {
+        self.wip_class_instances.push(instance);
+    }
+
+    /// Class instances rendered by the work in progress, in the order they were rendered
+    pub fn take_wip_class_instances(&mut self) -> Vec<Rc<ClassInstance>> {
+        std::mem::take(&mut self.wip_class_instances)
+    }
+
+    pub fn schedule_update(&mut self, lane: Lane) {
+        self.pending_lanes.insert(lane);
+
+        // Only a more urgent update interrupts the render in progress, which restarts from
+        // the current tree on the next tick. Anything else waits for that render to commit.
+        if self.wip_lane.is_some_and(|wip_lane| lane < wip_lane) {
+            self.abandon_work();
+        }
+    }
+
+    pub fn abandon_work(&mut self) {
+        // The lane of an abandoned render still has updates waiting for it
+        if let Some(wip_lane) = self.wip_lane.take() {
+            self.pending_lanes.insert(wip_lane);
+        }
+
+        // Children taken by the abandoned render are left for the next one, unless newer ones were set
+        if let Some(wip) = self.wip.take() {
+            if self.children.is_none() {
+                self.children = self.reclaim_children(&wip);
+            }
+        }
+
+        self.next_unit_of_work = None;
+        self.effects.clear();
+        self.wip_hooks.clear();
+        self.wip_class_instances.clear();
+    }
+
+    /// Drops a render that failed along with its lane, whose updates wait for the next one
+    pub fn discard_work(&mut self) {
+        self.wip_lane = None;
+        self.abandon_work();
+    }
+
+    /// Children a render took when it started, if they weren't those of the current tree
+    fn reclaim_children(&self, wip: &FiberCell) -> Option<Vec<Element>> {
+        let children = wip.borrow().element_children().clone()?;
+        wip.borrow_mut().set_element_children(None);
+        let current_children = self.current.as_ref().and_then(|current| current.borrow().element_children().clone());
+
+        if current_children.is_some_and(|current_children| Rc::ptr_eq(&current_children, &children)) {
+            return None;
+        }
+
+        Rc::try_unwrap(children).ok().map(|children| children.into_inner().into_iter().map(|child| *child).collect())
+    }
+
+    /// Starts rendering the most urgent pending lane, unless a render is in progress already
+    pub fn prepare_fresh_stack(&mut self) {
+        if self.wip.is_some() {
+            return;
+        }
+
+        let lane = match self.pending_lanes.highest_priority() {
+            Some(lane) => lane,
+            None => return,
+        };
+
+        let children = match self.children.take() {
+            Some(children) => Some(Rc::new(RefCell::new(children.into_iter().map(Box::new).collect()))),
+            None => self.current.as_ref().and_then(|current| current.borrow().element_children().clone()),
+        };
+
+        let mut root = Fiber::new_root();
+        root.set_element_children(children);
+
+        // Store the container's instance
+        root.set_dom_node(Rc::clone(&self.container));
+
+        // Set the current root as the alternate root
+        if let Some(current) = self.current.as_ref() {
+            // Updates below the root were marked on the current one
+            root.set_child_lanes(current.borrow().child_lanes());
+            root.set_alternate(Rc::clone(current));
+        }
+
+        self.pending_lanes.remove(lane);
+
+        // A render restarted from scratch claims instances from the start as well
+        if self.is_hydrating {
+            self.hydration = Some(Hydration::new(&self.container.borrow(), &*self.host));
+        }
+
+        // Make it the Work in Progress Root and the Next Unit of Work
+        let root = Rc::new(RefCell::new(Box::new(root)));
+        self.wip = Some(Rc::clone(&root));
+        self.next_unit_of_work = Some(root);
+        self.wip_lane = Some(lane);
+    }
+
+    /// Makes the work in progress tree the current one, once its effects are committed
+    pub fn finish_commit(&mut self) {
+        self.current = self.wip.take();
+        self.is_hydrating = false;
+        self.hydration = None;
+        self.next_unit_of_work = None;
+
+        if let Some(lane) = self.wip_lane.take() {
+            for (hook, rendered_len) in self.wip_hooks.drain(..) {
+                hook.borrow_mut().commit(lane, rendered_len);
+            }
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{IdleDeadline, MessageChannel};
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use super::{Error, YIELD_THRESHOLD_MS, FRAME_BUDGET_MS};
+
+/// Work run by a scheduler, which fails with what made a render fail
+pub type Task = Box<dyn FnOnce(&dyn Deadline) -> Result<(), Error>>;
+
+/// Tells the work loop when it has to give control back to the browser
+pub trait Deadline {
+    fn should_yield(&self) -> bool;
+}
+
+/// Decides when the work loop runs
+pub trait Scheduler {
+    fn schedule(&self, task: Task);
+
+    /// Runs the scheduled tasks right away. Only meaningful for schedulers
+    /// that don't run tasks on their own.
+    fn flush(&self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SchedulerKind {
+    IdleCallback,
+    MessageChannel,
+    AnimationFrame,
+    Manual,
+}
+
+impl SchedulerKind {
+    /// `requestIdleCallback` when the browser has it (Safari doesn't), a `MessageChannel` otherwise.
+    /// Off the browser, as when rendering on a server, work only runs when flushed.
+    pub fn detect() -> Self {
+        let window = match web_sys::window() {
+            Some(window) => window,
+            None => return SchedulerKind::Manual,
+        };
+
+        if js_sys::Reflect::has(&window, &JsValue::from_str("requestIdleCallback")).unwrap_or(false) {
+            SchedulerKind::IdleCallback
+        } else {
+            SchedulerKind::MessageChannel
+        }
+    }
+
+    pub fn create_scheduler(self) -> Rc<dyn Scheduler> {
+        match self {
+            SchedulerKind::IdleCallback => Rc::new(IdleCallbackScheduler),
+            SchedulerKind::MessageChannel => Rc::new(MessageChannelScheduler::new()),
+            SchedulerKind::AnimationFrame => Rc::new(AnimationFrameScheduler),
+            SchedulerKind::Manual => Rc::new(ManualScheduler::default()),
+        }
+    }
+}
+
+impl Deadline for IdleDeadline {
+    fn should_yield(&self) -> bool {
+        // A timed out callback has to make progress regardless of the remaining time
+        !self.did_timeout() && self.time_remaining() < YIELD_THRESHOLD_MS
+    }
+}
+
+/// Fixed amount of time starting when the task is run
+pub struct TimeSlice {
+    end: f64,
+}
+
+impl TimeSlice {
+    pub fn start() -> Self {
+        TimeSlice {
+            end: now() + FRAME_BUDGET_MS,
+        }
+    }
+}
+
+impl Deadline for TimeSlice {
+    fn should_yield(&self) -> bool {
+        now() >= self.end
+    }
+}
+
+/// Never yields, so the work loop runs to completion
+pub struct Unbounded;
+
+impl Deadline for Unbounded {
+    fn should_yield(&self) -> bool {
+        false
+    }
+}
+
+/// Milliseconds from the `performance` of the global object, which workers and Node have as well
+/// as windows, or from `Date` where there is none
+fn now() -> f64 {
+    match js_sys::Reflect::get(&js_sys::global(), &JsValue::from_str("performance")) {
+        Ok(performance) if performance.is_object() => performance.unchecked_into::<web_sys::Performance>().now(),
+        _ => js_sys::Date::now(),
+    }
+}
+
+pub struct IdleCallbackScheduler;
+
+impl Scheduler for IdleCallbackScheduler {
+    fn schedule(&self, task: Task) {
+        // What a task fails with is thrown in the browser's event loop
+        let callback = Closure::once_into_js(move |deadline: IdleDeadline| -> Result<(), JsValue> {
+            Ok(task(&deadline)?)
+        });
+
+        web_sys::window().unwrap()
+            .request_idle_callback(callback.unchecked_ref())
+            .unwrap();
+    }
+}
+
+/// Yields to the browser after every time slice by posting a message to itself, which
+/// unlike `setTimeout` isn't clamped to a minimum delay
+pub struct MessageChannelScheduler {
+    channel: MessageChannel,
+    tasks: Rc<RefCell<VecDeque<Task>>>,
+    _on_message: Closure<dyn FnMut() -> Result<(), JsValue>>,
+}
+
+impl MessageChannelScheduler {
+    pub fn new() -> Self {
+        let channel = MessageChannel::new().unwrap();
+        let tasks: Rc<RefCell<VecDeque<Task>>> = Rc::new(RefCell::new(VecDeque::new()));
+
+        let on_message = {
+            let tasks = Rc::clone(&tasks);
+
+            Closure::wrap(Box::new(move || {
+                // Tasks scheduled by these ones wait for the next message
+                let pending = tasks.borrow_mut().drain(..).collect::<Vec<Task>>();
+                let mut result = Ok(());
+
+                // One failing doesn't keep the others from running
+                for task in pending {
+                    result = result.and(task(&TimeSlice::start()));
+                }
+
+                Ok(result?)
+            }) as Box<dyn FnMut() -> Result<(), JsValue>>)
+        };
+
+        channel.port1().set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        MessageChannelScheduler {
+            channel,
+            tasks,
+            _on_message: on_message,
+        }
+    }
+}
+
+impl Scheduler for MessageChannelScheduler {
+    fn schedule(&self, task: Task) {
+        self.tasks.borrow_mut().push_back(task);
+        self.channel.port2().post_message(&JsValue::undefined()).unwrap();
+    }
+}
+
+pub struct AnimationFrameScheduler;
+
+impl Scheduler for AnimationFrameScheduler {
+    fn schedule(&self, task: Task) {
+        let callback = Closure::once_into_js(move || -> Result<(), JsValue> {
+            Ok(task(&TimeSlice::start())?)
+        });
+
+        web_sys::window().unwrap()
+            .request_animation_frame(callback.unchecked_ref())
+            .unwrap();
+    }
+}
+
+/// Keeps tasks until they are flushed, running each one to completion. Flushing stops at
+/// the first task that fails, leaving the others for the next flush.
+#[derive(Default)]
+pub struct ManualScheduler {
+    tasks: RefCell<VecDeque<Task>>,
+}
+
+impl Scheduler for ManualScheduler {
+    fn schedule(&self, task: Task) {
+        self.tasks.borrow_mut().push_back(task);
+    }
+
+    fn flush(&self) -> Result<(), Error> {
+        loop {
+            // Release the queue before running the task, which may schedule another one
+            let task = self.tasks.borrow_mut().pop_front();
+
+            match task {
+                Some(task) => task(&Unbounded)?,
+                None => return Ok(()),
+            }
+        }
+    }
+}
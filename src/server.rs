@@ -0,0 +1,446 @@
+use wasm_bindgen::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::rc::Rc;
+use super::{Context, Element, ElementProps, Error, FiberCell, FiberParentIterator, HostConfig, Lane, Node, Root, RootCell, release_fiber_tree};
+
+// Elements that can't have children, so they have no closing tag
+static VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source", "track", "wbr",
+];
+
+enum HtmlNode {
+    Element {
+        element_type: String,
+        attributes: Vec<(&'static str, String)>,
+        children: Vec<HtmlInstance>,
+    },
+    Text(String),
+}
+
+#[derive(Clone)]
+pub struct HtmlInstance(Rc<RefCell<HtmlNode>>);
+
+impl HtmlInstance {
+    pub fn container() -> Self {
+        HtmlInstance::new(HtmlNode::Element {
+            element_type: String::new(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+        })
+    }
+
+    fn new(node: HtmlNode) -> Self {
+        HtmlInstance(Rc::new(RefCell::new(node)))
+    }
+
+    fn from_node(node: &Node) -> &HtmlInstance {
+        node.downcast_ref::<HtmlInstance>().expect("node wasn't created by the string host")
+    }
+
+    pub fn inner_html(&self) -> String {
+        let mut html = String::new();
+
+        if let HtmlNode::Element { children, .. } = &*self.0.borrow() {
+            write_children(&mut html, children);
+        }
+
+        html
+    }
+
+    fn write_html(&self, html: &mut String) {
+        match &*self.0.borrow() {
+            HtmlNode::Element { element_type, attributes, children } => {
+                write_open_tag(html, element_type, attributes);
+                write_children(html, children);
+                write_close_tag(html, element_type);
+            },
+            HtmlNode::Text(text) => html.push_str(&escape(text, false)),
+        }
+    }
+
+    fn is_text(&self) -> bool {
+        matches!(&*self.0.borrow(), HtmlNode::Text(_))
+    }
+
+    fn with_children<T>(&self, f: impl FnOnce(&mut Vec<HtmlInstance>) -> T) -> Option<T> {
+        match &mut *self.0.borrow_mut() {
+            HtmlNode::Element { children, .. } => Some(f(children)),
+            HtmlNode::Text(_) => None,
+        }
+    }
+}
+
+fn write_open_tag(html: &mut String, element_type: &str, attributes: &[(&'static str, String)]) {
+    let _ = write!(html, "<{}", element_type);
+
+    for (name, value) in attributes {
+        let _ = write!(html, " {}=\"{}\"", name, escape(value, true));
+    }
+
+    html.push('>');
+}
+
+fn write_close_tag(html: &mut String, element_type: &str) {
+    if !VOID_ELEMENTS.contains(&element_type) {
+        let _ = write!(html, "</{}>", element_type);
+    }
+}
+
+fn write_children(html: &mut String, children: &[HtmlInstance]) {
+    for (i, child) in children.iter().enumerate() {
+        // Adjacent text nodes would be parsed back as a single one
+        if i > 0 && child.is_text() && children[i - 1].is_text() {
+            html.push_str("<!-- -->");
+        }
+
+        child.write_html(html);
+    }
+}
+
+fn escape(value: &str, is_attribute: bool) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' if is_attribute => escaped.push_str("&quot;"),
+            '\'' if is_attribute => escaped.push_str("&#x27;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Attributes as the DOM host would set them. Listeners can't be serialized, so they are left out.
+fn attributes(props: &ElementProps) -> Vec<(&'static str, String)> {
+    let mut attributes = Vec::new();
+
+    if let Some(class_name) = props.class_name() {
+        attributes.push(("class", class_name.clone()));
+    }
+
+    if let Some(input_type) = props.input_type() {
+        attributes.push(("type", input_type.clone()));
+    }
+
+    if let Some(input_value) = props.input_value() {
+        attributes.push(("value", input_value.clone()));
+    }
+
+    if let Some(input_placeholder) = props.input_placeholder() {
+        attributes.push(("placeholder", input_placeholder.clone()));
+    }
+
+    if props.input_checked() == Some(true) {
+        attributes.push(("checked", String::new()));
+    }
+
+    attributes
+}
+
+/// Builds a tree of HTML nodes, to be serialized once committed
+pub struct StringHost;
+
+impl HostConfig for StringHost {
+    fn create_instance(&self, element_type: &str, props: &ElementProps) -> Node {
+        Node::new(HtmlInstance::new(HtmlNode::Element {
+            element_type: String::from(element_type),
+            attributes: attributes(props),
+            children: Vec::new(),
+        }))
+    }
+
+    fn create_text_instance(&self, text: &str) -> Node {
+        Node::new(HtmlInstance::new(HtmlNode::Text(String::from(text))))
+    }
+
+    fn append_child(&self, parent: &Node, child: &Node) -> Result<(), JsValue> {
+        let child = HtmlInstance::from_node(child).clone();
+        HtmlInstance::from_node(parent).with_children(|children| children.push(child));
+
+        Ok(())
+    }
+
+    // The tree is only rendered once, so nothing is ever moved, removed or updated
+    fn insert_before(&self, parent: &Node, child: &Node, _before: &Node) -> Result<(), JsValue> {
+        self.append_child(parent, child)
+    }
+
+    fn remove_child(&self, _parent: &Node, _child: &Node) -> Result<(), JsValue> {
+        Ok(())
+    }
+
+    fn commit_update(&self, _instance: &Node, _prev_props: Option<&ElementProps>, _next_props: &ElementProps) {}
+
+    fn commit_text_update(&self, _instance: &Node, _prev_text: Option<&str>, _next_text: &str) {}
+
+    fn is_static(&self) -> bool {
+        true
+    }
+}
+
+/// Where the HTML of a Suspense boundary starts in the buffer, so it can be taken back
+#[derive(Clone, Copy)]
+struct BufferMark {
+    len: usize,
+    is_after_text: bool,
+}
+
+/// Writes HTML as fibers are rendered instead of building a tree: opening tags and text when their
+/// instances are created, and closing tags once their subtrees are complete
+pub struct StreamHost {
+    chunk_size: usize,
+    buffer: RefCell<String>,
+    chunks: RefCell<VecDeque<String>>,
+    is_after_text: Cell<bool>,
+    // Suspense boundaries being rendered, whose HTML stays in the buffer until they complete
+    held: Cell<usize>,
+}
+
+impl StreamHost {
+    pub fn new(chunk_size: usize) -> Self {
+        StreamHost {
+            chunk_size,
+            buffer: RefCell::new(String::new()),
+            chunks: RefCell::new(VecDeque::new()),
+            is_after_text: Cell::new(false),
+            held: Cell::new(0),
+        }
+    }
+
+    /// Keeps what is written from now on in the buffer, until it's released
+    fn hold(&self) -> BufferMark {
+        self.held.set(self.held.get() + 1);
+
+        BufferMark {
+            len: self.buffer.borrow().len(),
+            is_after_text: self.is_after_text.get(),
+        }
+    }
+
+    fn release(&self) {
+        self.held.set(self.held.get() - 1);
+        self.push_full_chunk();
+    }
+
+    /// Takes back what was written since `mark`
+    fn rewind(&self, mark: BufferMark) {
+        self.buffer.borrow_mut().truncate(mark.len);
+        self.is_after_text.set(mark.is_after_text);
+    }
+
+    fn push_full_chunk(&self) {
+        let mut buffer = self.buffer.borrow_mut();
+
+        if self.held.get() == 0 && buffer.len() >= self.chunk_size {
+            self.chunks.borrow_mut().push_back(std::mem::take(&mut *buffer));
+        }
+    }
+
+    fn take_chunk(&self) -> Option<String> {
+        self.chunks.borrow_mut().pop_front()
+    }
+
+    /// Whatever is left once the whole tree is rendered
+    fn take_rest(&self) -> Option<String> {
+        let rest = std::mem::take(&mut *self.buffer.borrow_mut());
+
+        Some(rest).filter(|rest| !rest.is_empty())
+    }
+}
+
+impl HostConfig for StreamHost {
+    fn create_instance(&self, element_type: &str, props: &ElementProps) -> Node {
+        write_open_tag(&mut self.buffer.borrow_mut(), element_type, &attributes(props));
+        self.is_after_text.set(false);
+
+        Node::new(HtmlInstance::new(HtmlNode::Element {
+            element_type: String::from(element_type),
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }))
+    }
+
+    fn create_text_instance(&self, text: &str) -> Node {
+        let mut buffer = self.buffer.borrow_mut();
+
+        if self.is_after_text.replace(true) {
+            buffer.push_str("<!-- -->");
+        }
+
+        buffer.push_str(&escape(text, false));
+
+        Node::new(HtmlInstance::new(HtmlNode::Text(String::new())))
+    }
+
+    fn complete_instance(&self, instance: &Node) {
+        if let HtmlNode::Element { element_type, .. } = &*HtmlInstance::from_node(instance).0.borrow() {
+            write_close_tag(&mut self.buffer.borrow_mut(), element_type);
+            self.is_after_text.set(false);
+            self.push_full_chunk();
+        }
+    }
+
+    // Everything is written as it is rendered, and nothing is ever committed
+    fn append_child(&self, _parent: &Node, _child: &Node) -> Result<(), JsValue> {
+        Ok(())
+    }
+
+    fn insert_before(&self, _parent: &Node, _child: &Node, _before: &Node) -> Result<(), JsValue> {
+        Ok(())
+    }
+
+    fn remove_child(&self, _parent: &Node, _child: &Node) -> Result<(), JsValue> {
+        Ok(())
+    }
+
+    fn commit_update(&self, _instance: &Node, _prev_props: Option<&ElementProps>, _next_props: &ElementProps) {}
+
+    fn commit_text_update(&self, _instance: &Node, _prev_text: Option<&str>, _next_text: &str) {}
+
+    fn is_static(&self) -> bool {
+        true
+    }
+}
+
+/// Render of an element tree that only makes progress when its next chunk of HTML is pulled
+#[wasm_bindgen]
+pub struct RenderStream {
+    context_ptr: *mut Context,
+    root: RootCell,
+    host: Rc<StreamHost>,
+    // Suspense boundaries being rendered, innermost last, with where their HTML starts
+    boundaries: Vec<(FiberCell, BufferMark)>,
+}
+
+impl RenderStream {
+    pub fn new(context_ptr: *mut Context, element: Element, chunk_size: usize) -> Self {
+        let host = Rc::new(StreamHost::new(chunk_size));
+
+        // The root is kept out of the context so its scheduled work doesn't pick it up
+        let mut context = Context::from_ptr(context_ptr);
+        let mut root = Root::new(context.next_root_id, Node::new(HtmlInstance::container()), Rc::clone(&host) as Rc<dyn HostConfig>);
+        context.next_root_id += 1;
+        let _ = Box::into_raw(context);
+
+        root.set_children(vec![element]);
+        root.schedule_update(Lane::Sync);
+        root.prepare_fresh_stack();
+
+        RenderStream {
+            context_ptr,
+            root: Rc::new(RefCell::new(root)),
+            host,
+            boundaries: Vec::new(),
+        }
+    }
+}
+
+impl RenderStream {
+    fn render_chunk(&mut self) -> Result<Option<String>, Error> {
+        loop {
+            if let Some(chunk) = self.host.take_chunk() {
+                return Ok(Some(chunk));
+            }
+
+            let wip_fiber = self.root.borrow().next_unit_of_work().map(Rc::clone);
+
+            let wip_fiber = match wip_fiber {
+                Some(wip_fiber) => wip_fiber,
+                None => return Ok(self.host.take_rest()),
+            };
+
+            self.enter_boundary(&wip_fiber);
+
+            let mut context = Context::from_ptr(self.context_ptr);
+            let previous_root = context.wip_root.replace(Rc::clone(&self.root));
+            let next_unit_of_work = context.perform_unit_of_work(wip_fiber);
+            context.wip_root = previous_root;
+            let _ = Box::into_raw(context);
+
+            match next_unit_of_work {
+                Ok(next_unit_of_work) => {
+                    self.leave_completed_boundaries(next_unit_of_work.as_ref());
+                    self.root.borrow_mut().set_next_unit_of_work(next_unit_of_work);
+                },
+                Err(error) => {
+                    // The stream ends with the error, without the HTML rendered so far
+                    let wip = self.root.borrow().wip().map(Rc::clone);
+
+                    if let Some(wip) = wip {
+                        release_fiber_tree(wip);
+                    }
+
+                    self.leave_completed_boundaries(None);
+                    self.host.take_rest();
+                    self.root.borrow_mut().discard_work();
+
+                    return Err(error);
+                },
+            }
+        }
+    }
+
+    /// Holds the HTML of a Suspense boundary until it completes. A boundary rendered again is
+    /// rendering its fallback, as something below it suspended, so its HTML so far is taken back.
+    fn enter_boundary(&mut self, fiber: &FiberCell) {
+        if !fiber.borrow().is_suspense_boundary() {
+            return;
+        }
+
+        match self.boundaries.last() {
+            Some((boundary, mark)) if Rc::ptr_eq(boundary, fiber) => self.host.rewind(*mark),
+            _ => self.boundaries.push((Rc::clone(fiber), self.host.hold())),
+        }
+    }
+
+    /// Releases the HTML of the boundaries `next_unit_of_work` isn't below anymore
+    fn leave_completed_boundaries(&mut self, next_unit_of_work: Option<&FiberCell>) {
+        while let Some((boundary, _)) = self.boundaries.last() {
+            let is_inside = next_unit_of_work.is_some_and(|next| {
+                Rc::ptr_eq(next, boundary) || next.parents().any(|parent| Rc::ptr_eq(&parent, boundary))
+            });
+
+            if is_inside {
+                break;
+            }
+
+            self.boundaries.pop();
+            self.host.release();
+        }
+    }
+}
+
+#[wasm_bindgen]
+impl RenderStream {
+    /// Renders until a chunk of HTML is ready, or returns `None` once everything was streamed.
+    /// Throws what made the render fail.
+    pub fn next_chunk(&mut self) -> Result<Option<String>, JsValue> {
+        Ok(self.render_chunk()?)
+    }
+}
+
+impl Iterator for RenderStream {
+    type Item = Result<String, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.render_chunk().transpose()
+    }
+}
+
+impl Drop for RenderStream {
+    fn drop(&mut self) {
+        let mut root = self.root.borrow_mut();
+
+        if let Some(wip) = root.wip().map(Rc::clone) {
+            release_fiber_tree(wip);
+        }
+
+        root.abandon_work();
+    }
+}
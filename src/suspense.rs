@@ -0,0 +1,70 @@
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use js_sys::{Function, Reflect};
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+use super::{Fiber, FiberCell, Root, RootCell, WorkMark};
+
+/// State of a Suspense boundary, shared by its fiber and all of its alternates
+#[derive(Default)]
+pub struct SuspenseBoundary {
+    // Work of the render in progress when the boundary was reached, which is rewound to
+    // when something below it suspends
+    mark: Cell<Option<WorkMark>>,
+    // Fiber of the latest render, kept weak as hooks keep theirs
+    fiber: RefCell<Option<Weak<RefCell<Box<Fiber>>>>>,
+    root: RefCell<Option<Weak<RefCell<Root>>>>,
+}
+
+impl SuspenseBoundary {
+    pub fn set_mark(&self, mark: WorkMark) {
+        self.mark.set(Some(mark));
+    }
+
+    pub fn take_mark(&self) -> Option<WorkMark> {
+        self.mark.take()
+    }
+
+    pub fn fiber(&self) -> Option<FiberCell> {
+        self.fiber.borrow().as_ref().and_then(Weak::upgrade)
+    }
+
+    pub fn root(&self) -> Option<RootCell> {
+        self.root.borrow().as_ref().and_then(Weak::upgrade)
+    }
+
+    pub fn set_rendered_by(&self, fiber: &FiberCell, root: &RootCell) {
+        *self.fiber.borrow_mut() = Some(Rc::downgrade(fiber));
+        *self.root.borrow_mut() = Some(Rc::downgrade(root));
+    }
+}
+
+/// Something a suspended component waits for, like a promise. Rust components suspend on one
+/// through `Hooks::suspend`.
+pub trait Wakeable {
+    /// Calls `callback` once it settles, whether it succeeded or not
+    fn when_settled(&self, callback: Box<dyn FnOnce()>);
+}
+
+/// Promise (or any thenable) thrown by a JS component
+pub struct Thenable(pub JsValue);
+
+impl Wakeable for Thenable {
+    fn when_settled(&self, callback: Box<dyn FnOnce()>) {
+        when_settled(&self.0, callback);
+    }
+}
+
+/// Whether a value thrown while rendering is a promise (or any thenable) the component waits for
+pub fn is_thenable(value: &JsValue) -> bool {
+    value.is_object() && Reflect::get(value, &JsValue::from_str("then")).is_ok_and(|then| then.is_function())
+}
+
+/// Calls `callback` once the thenable settles, whether it's fulfilled or rejected
+pub fn when_settled(thenable: &JsValue, callback: impl FnOnce() + 'static) {
+    let then = Reflect::get(thenable, &JsValue::from_str("then")).unwrap().unchecked_into::<Function>();
+
+    // Only one of the two is ever called, which frees the closure
+    let callback = Closure::once_into_js(move |_: JsValue| callback());
+    let _ = then.call2(thenable, &callback, &callback);
+}
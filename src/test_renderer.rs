@@ -0,0 +1,251 @@
+use wasm_bindgen::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::{Rc, Weak};
+use super::{Element, ElementProps, HostConfig, Node};
+
+/// In-memory node, so trees can be rendered and inspected off the browser
+pub enum TestNode {
+    Element {
+        element_type: String,
+        props: ElementProps,
+        children: Vec<TestInstance>,
+    },
+    Text(String),
+}
+
+#[derive(Clone)]
+pub struct TestInstance {
+    node: Rc<RefCell<TestNode>>,
+    // Shared by every handle to the node
+    parent: Rc<RefCell<Weak<RefCell<TestNode>>>>,
+    // Set once the host was told to release it, as the DOM host removes listeners
+    is_detached: Rc<Cell<bool>>,
+}
+
+impl TestInstance {
+    pub fn container() -> Self {
+        Self::new(TestNode::Element {
+            element_type: String::from("root"),
+            props: ElementProps::default(),
+            children: Vec::new(),
+        })
+    }
+
+    fn new(node: TestNode) -> Self {
+        TestInstance {
+            node: Rc::new(RefCell::new(node)),
+            parent: Rc::new(RefCell::new(Weak::new())),
+            is_detached: Rc::new(Cell::new(false)),
+        }
+    }
+
+    fn from_node(node: &Node) -> &TestInstance {
+        node.downcast_ref::<TestInstance>().expect("node wasn't created by the test host")
+    }
+
+    pub fn is_same(&self, other: &TestInstance) -> bool {
+        Rc::ptr_eq(&self.node, &other.node)
+    }
+
+    pub fn is_detached(&self) -> bool {
+        self.is_detached.get()
+    }
+
+    pub fn children(&self) -> Vec<TestInstance> {
+        match &*self.node.borrow() {
+            TestNode::Element { children, .. } => children.clone(),
+            TestNode::Text(_) => Vec::new(),
+        }
+    }
+
+    /// Markup of the children, in the same format as `Display`
+    pub fn inner_html(&self) -> String {
+        self.children().iter().map(ToString::to_string).collect()
+    }
+
+    fn remove(&self, child: &TestInstance) -> Option<usize> {
+        match &mut *self.node.borrow_mut() {
+            TestNode::Element { children, .. } => {
+                let position = children.iter().position(|node| node.is_same(child));
+
+                if let Some(position) = position {
+                    children.remove(position);
+                    *child.parent.borrow_mut() = Weak::new();
+                }
+
+                position
+            },
+            TestNode::Text(_) => None,
+        }
+    }
+
+    fn insert(&self, child: &TestInstance, before: Option<&TestInstance>) {
+        // Like the DOM, inserting a node that is in the tree already moves it
+        self.remove(child);
+
+        if let TestNode::Element { children, .. } = &mut *self.node.borrow_mut() {
+            let position = match before {
+                Some(before) => children.iter()
+                    .position(|node| node.is_same(before))
+                    .expect("reference node isn't a child of the parent"),
+                None => children.len(),
+            };
+
+            children.insert(position, child.clone());
+            *child.parent.borrow_mut() = Rc::downgrade(&self.node);
+        }
+    }
+}
+
+impl fmt::Display for TestInstance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &*self.node.borrow() {
+            TestNode::Element { element_type, props, children } => {
+                write!(f, "<{}", element_type)?;
+
+                if let Some(class_name) = props.class_name() {
+                    write!(f, " class=\"{}\"", class_name)?;
+                }
+
+                if let Some(input_type) = props.input_type() {
+                    write!(f, " type=\"{}\"", input_type)?;
+                }
+
+                if let Some(input_value) = props.input_value() {
+                    write!(f, " value=\"{}\"", input_value)?;
+                }
+
+                if let Some(input_placeholder) = props.input_placeholder() {
+                    write!(f, " placeholder=\"{}\"", input_placeholder)?;
+                }
+
+                if props.input_checked() == Some(true) {
+                    write!(f, " checked")?;
+                }
+
+                write!(f, ">")?;
+
+                for child in children {
+                    write!(f, "{}", child)?;
+                }
+
+                write!(f, "</{}>", element_type)
+            },
+            TestNode::Text(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// Renders into `TestInstance` trees
+pub struct TestHost;
+
+impl HostConfig for TestHost {
+    fn create_instance(&self, element_type: &str, props: &ElementProps) -> Node {
+        Node::new(TestInstance::new(TestNode::Element {
+            element_type: String::from(element_type),
+            props: props.clone(),
+            children: Vec::new(),
+        }))
+    }
+
+    fn create_text_instance(&self, text: &str) -> Node {
+        Node::new(TestInstance::new(TestNode::Text(String::from(text))))
+    }
+
+    fn append_child(&self, parent: &Node, child: &Node) -> Result<(), JsValue> {
+        TestInstance::from_node(parent).insert(TestInstance::from_node(child), None);
+
+        Ok(())
+    }
+
+    fn insert_before(&self, parent: &Node, child: &Node, before: &Node) -> Result<(), JsValue> {
+        TestInstance::from_node(parent).insert(TestInstance::from_node(child), Some(TestInstance::from_node(before)));
+
+        Ok(())
+    }
+
+    fn remove_child(&self, parent: &Node, child: &Node) -> Result<(), JsValue> {
+        TestInstance::from_node(parent)
+            .remove(TestInstance::from_node(child))
+            .expect("removed node isn't a child of the parent");
+
+        Ok(())
+    }
+
+    fn first_hydratable_child(&self, parent: &Node) -> Option<Node> {
+        TestInstance::from_node(parent).children().into_iter().next().map(Node::new)
+    }
+
+    fn next_hydratable_sibling(&self, instance: &Node) -> Option<Node> {
+        let instance = TestInstance::from_node(instance);
+        let parent = instance.parent.borrow().upgrade()?;
+
+        let sibling = match &*parent.borrow() {
+            TestNode::Element { children, .. } => children.iter()
+                .position(|sibling| sibling.is_same(instance))
+                .and_then(|position| children.get(position + 1).cloned()),
+            TestNode::Text(_) => None,
+        };
+
+        sibling.map(Node::new)
+    }
+
+    fn can_hydrate_instance(&self, instance: &Node, element_type: &str, _props: &ElementProps) -> bool {
+        match &*TestInstance::from_node(instance).node.borrow() {
+            TestNode::Element { element_type: instance_type, .. } => instance_type == element_type,
+            TestNode::Text(_) => false,
+        }
+    }
+
+    fn can_hydrate_text_instance(&self, instance: &Node, text: &str) -> bool {
+        match &*TestInstance::from_node(instance).node.borrow() {
+            TestNode::Text(instance_text) => instance_text == text,
+            TestNode::Element { .. } => false,
+        }
+    }
+
+    fn commit_hydration(&self, instance: &Node, props: &ElementProps) {
+        self.commit_update(instance, None, props);
+    }
+
+    fn describe_instance(&self, instance: &Node) -> String {
+        let instance = TestInstance::from_node(instance);
+
+        match &*instance.node.borrow() {
+            TestNode::Text(text) => format!("\"{}\"", text),
+            TestNode::Element { .. } => instance.to_string(),
+        }
+    }
+
+    fn detach_instance(&self, instance: &Node, _props: &ElementProps) {
+        TestInstance::from_node(instance).is_detached.set(true);
+    }
+
+    fn commit_update(&self, instance: &Node, _prev_props: Option<&ElementProps>, next_props: &ElementProps) {
+        if let TestNode::Element { props, .. } = &mut *TestInstance::from_node(instance).node.borrow_mut() {
+            *props = next_props.clone();
+        }
+    }
+
+    fn commit_text_update(&self, instance: &Node, _prev_text: Option<&str>, next_text: &str) {
+        if let TestNode::Text(text) = &mut *TestInstance::from_node(instance).node.borrow_mut() {
+            *text = String::from(next_text);
+        }
+    }
+}
+
+pub fn element(element_type: &str, class_name: Option<&str>, children: Vec<Box<Element>>) -> Box<Element> {
+    // Added one by one, as they aren't a list
+    let mut builder = children.into_iter().fold(Element::tag(element_type), |builder, child| builder.child(child));
+
+    if let Some(class_name) = class_name {
+        builder = builder.class(class_name);
+    }
+
+    builder.build()
+}
+
+pub fn text(value: &str) -> Box<Element> {
+    Element::text(value)
+}
@@ -0,0 +1,12 @@
+use wasm_bindgen::prelude::*;
+
+// Pointers handed to JS are typed apart, so they can't be passed where another one is expected.
+// An element handle is consumed by the export it's given to and can't be used again.
+#[wasm_bindgen(typescript_custom_section)]
+const HANDLES: &str = r#"
+export type ContextHandle = number & { readonly __reactron: "Context" };
+export type ElementHandle = number & { readonly __reactron: "Element" };
+export type PropsHandle = number & { readonly __reactron: "ElementProps" };
+export type RootId = number & { readonly __reactron: "Root" };
+export type StateTuple<T = any> = [T, (value: T) => void];
+"#;
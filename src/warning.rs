@@ -0,0 +1,30 @@
+use std::cell::RefCell;
+use wasm_bindgen::prelude::*;
+
+/// Where warnings are reported
+pub type WarningSink = Box<dyn Fn(&str)>;
+
+thread_local! {
+    static SINK: RefCell<WarningSink> = RefCell::new(Box::new(report));
+}
+
+/// Reports a problem that doesn't stop rendering to the warning sink
+pub fn warn(message: &str) {
+    let message = format!("Reactron: {}", message);
+
+    SINK.with(|sink| sink.borrow()(&message));
+}
+
+/// Replaces where warnings are reported, e.g. to collect or silence them in tests
+pub fn set_warning_sink(sink: WarningSink) {
+    SINK.with(|current| *current.borrow_mut() = sink);
+}
+
+/// Default sink, the browser's console or stderr off the browser
+fn report(message: &str) {
+    if cfg!(target_arch = "wasm32") {
+        web_sys::console::warn_1(&JsValue::from_str(message));
+    } else {
+        eprintln!("{}", message);
+    }
+}